@@ -1,5 +1,6 @@
 use crate::vec3::Vec3;
 use crate::ray::Ray;
+use crate::debug::count_box_test;
 
 #[derive(Clone)]
 pub struct AABB {
@@ -9,6 +10,8 @@ pub struct AABB {
 
 impl AABB {
     pub fn hit(&self, ray: &Ray, tmin: f32, tmax: f32) -> bool {
+        count_box_test();
+
         let min = (self.min - ray.origin) / ray.direction;
         let max = (self.max - ray.origin) / ray.direction;
 
@@ -0,0 +1,80 @@
+use crate::{adaptive::PixelEstimate, film::{Film, Filter}, framebuffer::Framebuffer, prelude::Vec3};
+
+// Per pixel running sums of an image's samples, so a render can be
+// refined over several passes and looked at in between. Estimates keep the
+// samples taken in each pixel while the film gathers their filtered splats
+#[derive(Debug, Clone)]
+pub struct Accumulator {
+    width: usize,
+    height: usize,
+    pixels: Vec<PixelEstimate>,
+    film: Film,
+}
+
+impl Accumulator {
+    pub fn new(width: usize, height: usize, filter: Filter) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![PixelEstimate::default(); width * height],
+            film: Film::new(width, height, filter),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, (x, y): (usize, usize)) -> &PixelEstimate {
+        &self.pixels[y * self.width + x]
+    }
+
+    // Estimates laid out row after row, starting from the bottom one
+    pub fn estimates(&self) -> &[PixelEstimate] {
+        &self.pixels
+    }
+
+    pub fn film(&self) -> &Film {
+        &self.film
+    }
+
+    pub fn film_mut(&mut self) -> &mut Film {
+        &mut self.film
+    }
+
+    pub fn add(&mut self, (x, y): (usize, usize), estimate: &PixelEstimate) {
+        self.pixels[y * self.width + x].merge(estimate);
+    }
+
+    // Splats of samples rendered apart in a region of the film
+    pub fn merge_film(&mut self, film: &Film) {
+        self.film.merge(film);
+    }
+
+    pub fn samples(&self, pixel: (usize, usize)) -> u32 {
+        self.get(pixel).samples()
+    }
+
+    // Mean radiance of the pixel's own samples so far, black without any
+    pub fn mean(&self, pixel: (usize, usize)) -> Vec3 {
+        self.get(pixel).mean()
+    }
+
+    // Filtered radiance so far, black for pixels without samples nearby
+    pub fn radiance(&self, pixel: (usize, usize)) -> Vec3 {
+        self.film.get(pixel)
+    }
+
+    pub fn framebuffer(&self) -> Framebuffer {
+        self.film.framebuffer()
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.iter_mut().for_each(|pixel| *pixel = PixelEstimate::default());
+        self.film.clear();
+    }
+}
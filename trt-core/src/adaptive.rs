@@ -0,0 +1,192 @@
+use crate::{accumulator::Accumulator, color::luminance, film::{Film, Filter}, framebuffer::Framebuffer, tile::Tile, prelude::Vec3, scene::{AdaptiveSampling, OutlierRejection}};
+
+use std::ops::Range;
+
+// Running mean and variance of a pixel's samples
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixelEstimate {
+    summed: Vec3,
+    luminance: f64,
+    luminance_squared: f64,
+    samples: u32,
+}
+
+impl PixelEstimate {
+    pub fn add(&mut self, radiance: Vec3) {
+        let luminance = luminance(radiance) as f64;
+
+        self.summed += radiance;
+        self.luminance += luminance;
+        self.luminance_squared += luminance * luminance;
+        self.samples += 1;
+    }
+
+    pub fn merge(&mut self, other: &PixelEstimate) {
+        self.summed += other.summed;
+        self.luminance += other.luminance;
+        self.luminance_squared += other.luminance_squared;
+        self.samples += other.samples;
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn mean(&self) -> Vec3 {
+        if self.samples == 0 {
+            return Vec3::splat(0.)
+        }
+
+        self.summed / self.samples as f32
+    }
+
+    // Mean and unbiased variance of the samples' luminance, from 2 samples
+    fn luminance_moments(&self) -> (f64, f64) {
+        let samples = self.samples as f64;
+        let mean = self.luminance / samples;
+        let variance = (self.luminance_squared / samples - mean * mean).max(0.) * samples / (samples - 1.);
+
+        (mean, variance)
+    }
+
+    // Standard error of the mean, measured on the gamma corrected luminance
+    // the way the pixel will be displayed
+    pub fn error(&self) -> f32 {
+        if self.samples < 2 {
+            return std::f32::INFINITY
+        }
+
+        let (mean, variance) = self.luminance_moments();
+        let std_error = (variance / self.samples as f64).sqrt();
+
+        // Derivative of the square root gamma, bounded for black pixels
+        (std_error / (2. * mean.sqrt()).max(1e-2)) as f32
+    }
+
+    // `radiance` of a new sample, scaled down if it stands out from the
+    // samples so far
+    pub fn reject_outlier(&self, radiance: Vec3, rejection: &OutlierRejection) -> Vec3 {
+        if self.samples < rejection.min_samples.max(2) {
+            return radiance
+        }
+
+        let (mean, variance) = self.luminance_moments();
+        let bound = (mean + rejection.deviations as f64 * variance.sqrt()) as f32;
+        let luminance = luminance(radiance);
+
+        if luminance > bound {
+            radiance * (bound / luminance)
+        } else {
+            radiance
+        }
+    }
+}
+
+// Spreads a total sample budget over the image in passes, giving the
+// noisiest unconverged pixels more samples each time
+#[derive(Debug, Clone)]
+pub struct AdaptiveRenderer {
+    settings: AdaptiveSampling,
+    budget: u64,
+    spent: u64,
+    accumulator: Accumulator,
+    // Pixels to sample, the others staying black
+    region: Tile,
+}
+
+impl AdaptiveRenderer {
+    pub fn new(width: usize, height: usize, filter: Filter, settings: AdaptiveSampling, budget: u64) -> Self {
+        Self {
+            settings,
+            budget,
+            spent: 0,
+            accumulator: Accumulator::new(width, height, filter),
+            region: Tile::new(0..width, 0..height),
+        }
+    }
+
+    // Only samples the pixels of `region`
+    pub fn cropped(self, region: Tile) -> Self {
+        Self { region, ..self }
+    }
+
+    pub fn spent(&self) -> u64 {
+        self.spent
+    }
+
+    // Samples gathered so far, to preview the render between passes
+    pub fn accumulator(&self) -> &Accumulator {
+        &self.accumulator
+    }
+
+    fn pixel(&self, index: usize) -> (usize, usize) {
+        let width = self.accumulator.width();
+        (index % width, index / width)
+    }
+
+    // Pixels to sample next with the indices of their samples, empty once
+    // every pixel converged or the budget ran out
+    pub fn next_pass(&self) -> Vec<((usize, usize), Range<u32>)> {
+        let AdaptiveSampling { min_samples, max_samples, noise_threshold } = self.settings;
+        let estimates = self.accumulator.estimates();
+
+        if self.spent == 0 {
+            return self.region.pixels()
+                .map(|pixel| (pixel, 0..min_samples.min(max_samples)))
+                .collect()
+        }
+
+        let mut noisy = estimates
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| self.region.contains(self.pixel(index)))
+            .filter(|(_, estimate)| estimate.samples < max_samples && estimate.error() > noise_threshold)
+            .map(|(index, estimate)| (index, estimate.error(), estimate.samples))
+            .collect::<Vec<_>>();
+        noisy.sort_by(|(_, a, _), (_, b, _)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut remaining = self.budget.saturating_sub(self.spent);
+        let mut pass = Vec::with_capacity(noisy.len());
+
+        for (index, _, samples) in noisy {
+            let count = (min_samples.max(1).min(max_samples - samples) as u64).min(remaining);
+            if count == 0 {
+                break
+            }
+
+            remaining -= count;
+            pass.push((self.pixel(index), samples..samples + count as u32));
+        }
+
+        pass
+    }
+
+    pub fn record(&mut self, pixel: (usize, usize), estimate: &PixelEstimate) {
+        self.accumulator.add(pixel, estimate);
+        self.spent += estimate.samples as u64;
+    }
+
+    // Splats of the recorded samples, rendered apart
+    pub fn merge_film(&mut self, film: &Film) {
+        self.accumulator.merge_film(film);
+    }
+
+    pub fn framebuffer(&self) -> Framebuffer {
+        self.accumulator.framebuffer()
+    }
+
+    // Samples spent per pixel, from blue for none to red for `max_samples`
+    pub fn sample_heatmap(&self) -> Framebuffer {
+        let max_samples = self.settings.max_samples.max(1) as f32;
+        let pixels = self.accumulator
+            .estimates()
+            .iter()
+            .map(|estimate| {
+                let t = (estimate.samples as f32 / max_samples).min(1.);
+                Vec3::new(t, 1. - (2. * t - 1.).abs(), 1. - t)
+            })
+            .collect();
+
+        Framebuffer::from_pixels(self.accumulator.width(), self.accumulator.height(), pixels)
+    }
+}
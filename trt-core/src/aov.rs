@@ -0,0 +1,236 @@
+use crate::{framebuffer::Framebuffer, prelude::{HitRecord, Ray, Vec3}, utils::hash};
+
+use std::collections::HashMap;
+
+// Arbitrary output variables, describing what the camera rays first hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    Albedo,
+    // World space shading normal
+    Normal,
+    // Distance from the camera
+    Depth,
+    Position,
+    Uv,
+    MaterialId,
+    ObjectId,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 7] = [Aov::Albedo, Aov::Normal, Aov::Depth, Aov::Position, Aov::Uv, Aov::MaterialId, Aov::ObjectId];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Position => "position",
+            Aov::Uv => "uv",
+            Aov::MaterialId => "material_id",
+            Aov::ObjectId => "object_id",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Aov::ALL.iter().copied().find(|aov| aov.name() == name)
+    }
+
+    // Number of values per pixel
+    pub fn channels(&self) -> usize {
+        match self {
+            Aov::Albedo | Aov::Normal | Aov::Position => 3,
+            Aov::Uv => 2,
+            Aov::Depth | Aov::MaterialId | Aov::ObjectId => 1,
+        }
+    }
+}
+
+// First hit of a camera ray
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceSample {
+    pub albedo: Vec3,
+    pub normal: Vec3,
+    pub depth: f32,
+    pub position: Vec3,
+    pub uv: (f32, f32),
+    pub material: usize,
+    pub object: usize,
+}
+
+impl SurfaceSample {
+    pub fn new(ray: &Ray, rec: &HitRecord) -> Self {
+        Self {
+            albedo: rec.mat.albedo(rec),
+            normal: rec.normal,
+            depth: rec.t * ray.direction.len(),
+            position: rec.p,
+            uv: (rec.u, rec.v),
+            material: rec.mat.id(),
+            object: rec.object,
+        }
+    }
+}
+
+// Running sums of a pixel's first hits. Albedo, normal and UV average over
+// every sample, misses counting as zero, depth and position over the hits
+// only, and ids come from the first sample
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixelAovs {
+    albedo: Vec3,
+    normal: Vec3,
+    depth: f32,
+    position: Vec3,
+    uv: Vec3,
+    material: Option<usize>,
+    object: Option<usize>,
+    hits: u32,
+    samples: u32,
+}
+
+impl PixelAovs {
+    pub fn add(&mut self, hit: Option<SurfaceSample>) {
+        let first = self.samples == 0;
+        self.samples += 1;
+
+        let hit = match hit {
+            Some(hit) => hit,
+            None => return,
+        };
+
+        self.albedo += hit.albedo;
+        self.normal += hit.normal;
+        self.depth += hit.depth;
+        self.position += hit.position;
+        self.uv += Vec3::new(hit.uv.0, hit.uv.1, 0.);
+        self.hits += 1;
+
+        if first {
+            self.material = Some(hit.material);
+            self.object = Some(hit.object);
+        }
+    }
+}
+
+fn mean(summed: Vec3, count: u32) -> Vec3 {
+    if count == 0 {
+        return Vec3::splat(0.)
+    }
+
+    summed / count as f32
+}
+
+// AOVs of a whole image. Ids are numbered from 1 in the order pixels first
+// show them, row after row from the bottom one, 0 being the background
+#[derive(Debug, Clone)]
+pub struct AovBuffers {
+    width: usize,
+    height: usize,
+    pixels: Vec<PixelAovs>,
+    material_ids: Vec<u32>,
+    object_ids: Vec<u32>,
+}
+
+impl AovBuffers {
+    // `pixels` are laid out row after row, starting from the bottom one
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<PixelAovs>) -> Self {
+        assert_eq!(pixels.len(), width * height, "AOV buffers and pixels dimension mismatch");
+
+        let number = |key: fn(&PixelAovs) -> Option<usize>| {
+            let mut ids = HashMap::new();
+            pixels
+                .iter()
+                .map(|pixel| match key(pixel) {
+                    Some(key) => {
+                        let next = ids.len() as u32 + 1;
+                        *ids.entry(key).or_insert(next)
+                    },
+                    None => 0,
+                })
+                .collect::<Vec<_>>()
+        };
+        let material_ids = number(|pixel| pixel.material);
+        let object_ids = number(|pixel| pixel.object);
+
+        Self { width, height, pixels, material_ids, object_ids }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Raw value of a pixel, padded with zeros past the AOV's channels.
+    // Depth is infinite where nothing was hit
+    pub fn get(&self, aov: Aov, (x, y): (usize, usize)) -> Vec3 {
+        let index = y * self.width + x;
+        let pixel = &self.pixels[index];
+
+        match aov {
+            Aov::Albedo => mean(pixel.albedo, pixel.samples),
+            Aov::Normal => mean(pixel.normal, pixel.samples),
+            Aov::Depth if pixel.hits == 0 => Vec3::new(std::f32::INFINITY, 0., 0.),
+            Aov::Depth => Vec3::new(pixel.depth / pixel.hits as f32, 0., 0.),
+            Aov::Position => mean(pixel.position, pixel.hits),
+            Aov::Uv => mean(pixel.uv, pixel.samples),
+            Aov::MaterialId => Vec3::new(self.material_ids[index] as f32, 0., 0.),
+            Aov::ObjectId => Vec3::new(self.object_ids[index] as f32, 0., 0.),
+        }
+    }
+
+    // `aov.channels()` values per pixel, laid out like `Framebuffer::pixels`
+    pub fn values(&self, aov: Aov) -> Vec<f32> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .flat_map(|pixel| {
+                let value = self.get(aov, pixel);
+                vec![value.x(), value.y(), value.z()].into_iter().take(aov.channels())
+            })
+            .collect()
+    }
+
+    // Displayable image of the AOV: normals are mapped from [-1, 1], depth
+    // and position are scaled to what was hit and ids get random colors
+    pub fn framebuffer(&self, aov: Aov) -> Framebuffer {
+        let pixels = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|pixel| self.get(aov, pixel))
+            .collect::<Vec<_>>();
+
+        let hits = self.pixels.iter().zip(&pixels).filter(|(pixel, _)| pixel.hits > 0).map(|(_, value)| *value);
+        let (min, max) = hits.fold((Vec3::splat(std::f32::MAX), Vec3::splat(std::f32::MIN)), |(min, max), value| {
+            (
+                Vec3::new(min.x().min(value.x()), min.y().min(value.y()), min.z().min(value.z())),
+                Vec3::new(max.x().max(value.x()), max.y().max(value.y()), max.z().max(value.z())),
+            )
+        });
+
+        let pixels = pixels
+            .into_iter()
+            .map(|value| match aov {
+                Aov::Albedo | Aov::Uv => value,
+                Aov::Normal => (value + Vec3::splat(1.)) * 0.5,
+                Aov::Depth if value.x().is_infinite() => Vec3::splat(1.),
+                Aov::Depth => Vec3::splat(value.x() / max.x().max(1e-6)),
+                Aov::Position => (value - min) / (max - min + Vec3::splat(1e-6)),
+                Aov::MaterialId | Aov::ObjectId => id_color(value.x() as u64),
+            })
+            .collect();
+
+        Framebuffer::from_pixels(self.width, self.height, pixels)
+    }
+}
+
+// Random color of an id, black for 0
+pub(crate) fn id_color(id: u64) -> Vec3 {
+    if id == 0 {
+        return Vec3::splat(0.)
+    }
+
+    let bits = hash(&[id]);
+    let channel = |shift: u64| 0.2 + 0.8 * ((bits >> shift) & 0xff) as f32 / 255.;
+
+    Vec3::new(channel(0), channel(8), channel(16))
+}
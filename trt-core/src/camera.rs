@@ -1,6 +1,7 @@
 use crate::prelude::{Vec3, Ray};
-use crate::utils::{random_in_unit_disk, Rng, thread_rng};
+use crate::utils::{random_in_unit_disk, Rng};
 
+#[derive(Debug, Clone)]
 pub struct Camera {
     origin: Vec3,
     lower_left_corner: Vec3,
@@ -18,19 +19,20 @@ pub struct Camera {
 }
 
 impl Camera {
-    pub fn get_ray(&self, s: f32, t: f32) -> Ray {
-        let mut rng = thread_rng();
-
+    pub fn get_ray(&self, s: f32, t: f32, mut rng: impl Rng) -> Ray {
         let rd = self.lens_radius * random_in_unit_disk(&mut rng);
         let offset = self.u * rd.x() + self.v * rd.y();
         let time = rng.gen_range(self.time_frame.0, self.time_frame.1);
+        let seed = rng.gen();
 
         let direction = self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset;
 
         Ray {
             origin: self.origin + offset,
             direction,
-            time
+            time,
+            wavelength: None,
+            seed,
         }
     }
 }
@@ -2,6 +2,18 @@ use crate::prelude::Vec3;
 
 pub struct Color(pub u8, pub u8, pub u8);
 
+// Relative luminance of a linear sRGB color
+pub fn luminance(color: Vec3) -> f32 {
+    Vec3::dot(color, Vec3::new(0.2126, 0.7152, 0.0722))
+}
+
+impl Color {
+    // Gamma corrects and clamps linear radiance
+    pub fn from_linear(radiance: Vec3) -> Self {
+        radiance.max(Vec3::splat(0.)).sqrt().into()
+    }
+}
+
 impl From<Vec3> for Color {
     fn from(vec: Vec3) -> Self {
         let as_rgb = (vec * 255.99).min(Vec3::splat(255));
@@ -0,0 +1,180 @@
+use crate::prelude::{Hit, Ray, Vec3};
+use crate::aov::id_color;
+
+#[cfg(feature = "traversal-stats")]
+use std::cell::Cell;
+
+#[cfg(feature = "traversal-stats")]
+thread_local! {
+    // Bounding box and primitive intersection tests done by this thread
+    static TESTS: Cell<(u64, u64)> = Cell::new((0, 0));
+}
+
+// Both are no-ops without the `traversal-stats` feature, as they sit on
+// the path of every ray
+#[inline(always)]
+pub(crate) fn count_box_test() {
+    #[cfg(feature = "traversal-stats")]
+    TESTS.with(|tests| {
+        let (boxes, primitives) = tests.get();
+        tests.set((boxes + 1, primitives));
+    });
+}
+
+#[inline(always)]
+pub(crate) fn count_primitive_test() {
+    #[cfg(feature = "traversal-stats")]
+    TESTS.with(|tests| {
+        let (boxes, primitives) = tests.get();
+        tests.set((boxes, primitives + 1));
+    });
+}
+
+#[cfg(feature = "traversal-stats")]
+fn tests() -> (u64, u64) {
+    TESTS.with(Cell::get)
+}
+
+// Intersection tests from blue for none to red for this many and more
+#[cfg(feature = "traversal-stats")]
+const HEATMAP_TESTS: f32 = 1024.;
+
+// Shades pixels by what their camera rays first hit instead of the light
+// they bring, to see why a scene looks wrong. Misses are black
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugView {
+    // Geometric normals mapped from [-1, 1]
+    Normal,
+    Uv,
+    // From white for the nearest point of the world's bounding box to black
+    // for its farthest corner
+    Distance,
+    // A random color per material
+    MaterialId,
+    // Heatmap of the bounding box and primitive intersection tests the ray
+    // took, misses included, on a logarithmic scale. Only built with the
+    // `traversal-stats` feature
+    #[cfg(feature = "traversal-stats")]
+    Traversal,
+}
+
+impl DebugView {
+    pub const ALL: &'static [DebugView] = &[
+        DebugView::Normal,
+        DebugView::Uv,
+        DebugView::Distance,
+        DebugView::MaterialId,
+        #[cfg(feature = "traversal-stats")]
+        DebugView::Traversal,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DebugView::Normal => "normal",
+            DebugView::Uv => "uv",
+            DebugView::Distance => "distance",
+            DebugView::MaterialId => "material_id",
+            #[cfg(feature = "traversal-stats")]
+            DebugView::Traversal => "traversal",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        DebugView::ALL.iter().copied().find(|view| view.name() == name)
+    }
+
+    // Why `name` isn't a view of this build, when a cargo feature leaves it out
+    pub fn missing_reason(name: &str) -> Option<&'static str> {
+        match name {
+            #[cfg(not(feature = "traversal-stats"))]
+            "traversal" => Some("traversal view needs the traversal-stats feature"),
+            _ => None,
+        }
+    }
+
+    pub fn shade(&self, ray: &Ray, world: &impl Hit) -> Vec3 {
+        #[cfg(feature = "traversal-stats")]
+        let (boxes, primitives) = tests();
+        let rec = world.hit(ray, 0.001, std::f32::MAX);
+
+        match (self, rec) {
+            #[cfg(feature = "traversal-stats")]
+            (DebugView::Traversal, _) => {
+                let (boxes_after, primitives_after) = tests();
+                let count = (boxes_after - boxes) + (primitives_after - primitives);
+                heatmap((count as f32 + 1.).log2() / (HEATMAP_TESTS + 1.).log2())
+            },
+            (_, None) => Vec3::splat(0.),
+            (DebugView::Normal, Some(rec)) => (rec.normal.unit() + Vec3::splat(1.)) * 0.5,
+            (DebugView::Uv, Some(rec)) => Vec3::new(rec.u, rec.v, 0.),
+            (DebugView::Distance, Some(rec)) => {
+                let distance = rec.t * ray.direction.len();
+                let (near, far) = match world.bounding_box(ray.time, ray.time) {
+                    Some(bbox) => {
+                        let (to_min, to_max) = (bbox.min - ray.origin, bbox.max - ray.origin);
+                        let outside = to_min.max(Vec3::splat(0.)) - to_max.min(Vec3::splat(0.));
+                        let farthest = (to_min * to_min).max(to_max * to_max).sqrt();
+                        (outside.len(), farthest.len())
+                    },
+                    None => (0., distance),
+                };
+
+                Vec3::splat(1. - ((distance - near) / (far - near).max(1e-6)).max(0.).min(1.))
+            },
+            (DebugView::MaterialId, Some(rec)) => id_color(rec.mat.id() as u64),
+        }
+    }
+}
+
+// From blue for 0 through green to red for 1
+#[cfg(feature = "traversal-stats")]
+fn heatmap(t: f32) -> Vec3 {
+    let t = t.max(0.).min(1.);
+    Vec3::new(t, 1. - (2. * t - 1.).abs(), 1. - t)
+}
+
+#[cfg(all(test, feature = "traversal-stats"))]
+mod tests {
+    use super::*;
+    use crate::hit::{BVHNode, Sphere};
+    use crate::material::MaterialBuilderExt;
+    use crate::utils::{Pcg32, SeedableRng};
+
+    use std::sync::Arc;
+
+    // Down the x axis, through every sphere below
+    fn ray() -> Ray {
+        Ray { origin: Vec3::new(-10., 0., 0.), direction: Vec3::new(1., 0., 0.), time: 0., wavelength: None, seed: 0 }
+    }
+
+    fn sphere(x: i32) -> Arc<impl Hit> {
+        Arc::new(Sphere::builder().center((x, 0, 0)).radius(1).diffuse_color((1, 1, 1)))
+    }
+
+    fn row_of_spheres() -> impl Hit {
+        let mut spheres = (0..16).map(|i| sphere(3 * i)).collect::<Vec<_>>();
+        BVHNode::new(&mut spheres, 0., 1., Pcg32::seed_from_u64(0))
+    }
+
+    fn traversal_tests(world: &impl Hit) -> u64 {
+        let (boxes, primitives) = tests();
+        world.hit(&ray(), 0.001, std::f32::MAX);
+        let (boxes_after, primitives_after) = tests();
+
+        (boxes_after - boxes) + (primitives_after - primitives)
+    }
+
+    #[test]
+    fn bvhs_take_more_tests_than_a_sphere() {
+        assert_eq!(traversal_tests(&sphere(0)), 1);
+        assert!(traversal_tests(&row_of_spheres()) > 16);
+    }
+
+    #[test]
+    fn heatmap_warms_up_with_tests() {
+        let cold = DebugView::Traversal.shade(&ray(), &sphere(0));
+        let warm = DebugView::Traversal.shade(&ray(), &row_of_spheres());
+
+        assert!(warm.x() > cold.x() && warm.z() < cold.z());
+    }
+}
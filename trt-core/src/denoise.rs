@@ -0,0 +1,211 @@
+use crate::{aov::{Aov, AovBuffers}, framebuffer::Framebuffer, prelude::Vec3};
+
+// B3 spline, the à-trous wavelet's smoothing kernel
+const KERNEL: [f32; 5] = [1. / 16., 1. / 4., 3. / 8., 1. / 4., 1. / 16.];
+
+// Edge-avoiding à-trous wavelet filter, following Dammertz et al. (2010):
+// blurs the image with a 5x5 kernel spreading twice as far on each pass,
+// neighbours counting less the more their color, normal, depth or albedo
+// differ. Sigmas scale these differences, lower ones keeping more edges,
+// and a sigma of zero leaves its feature out
+#[derive(Debug, Clone, Copy)]
+pub struct Denoiser {
+    pub iterations: u32,
+    // Of the colors compressed to [0, 1), halved on every pass
+    pub sigma_color: f32,
+    pub sigma_normal: f32,
+    // Relative to the pixel's depth and the pass's step
+    pub sigma_depth: f32,
+    pub sigma_albedo: f32,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            iterations: 4,
+            sigma_color: 0.5,
+            sigma_normal: 0.3,
+            sigma_depth: 0.05,
+            sigma_albedo: 0.1,
+        }
+    }
+}
+
+struct Features {
+    normal: Vec3,
+    depth: f32,
+    albedo: Vec3,
+}
+
+impl Denoiser {
+    // Filters the linear radiance of `image`, whose AOVs are `aovs`
+    pub fn denoise(&self, image: &Framebuffer, aovs: &AovBuffers) -> Framebuffer {
+        let (width, height) = (image.width(), image.height());
+        assert!(aovs.width() == width && aovs.height() == height, "Image and AOVs dimension mismatch");
+
+        let features = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|pixel| Features {
+                normal: aovs.get(Aov::Normal, pixel),
+                depth: aovs.get(Aov::Depth, pixel).x(),
+                albedo: aovs.get(Aov::Albedo, pixel),
+            })
+            .collect::<Vec<_>>();
+
+        let mut image = image.clone();
+        let mut sigma_color = self.sigma_color;
+
+        for iteration in 0..self.iterations {
+            let step = 1 << iteration;
+            let mut filtered = Framebuffer::new(width, height);
+
+            for y in 0..height {
+                for x in 0..width {
+                    let center = &features[y * width + x];
+                    let color = compress(image.get((x, y)));
+
+                    let (mut summed, mut weights) = (Vec3::splat(0.), 0.);
+
+                    for (j, ky) in KERNEL.iter().enumerate() {
+                        for (i, kx) in KERNEL.iter().enumerate() {
+                            let qx = x as isize + (i as isize - 2) * step;
+                            let qy = y as isize + (j as isize - 2) * step;
+                            if qx < 0 || qy < 0 || qx >= width as isize || qy >= height as isize {
+                                continue
+                            }
+
+                            let q = (qx as usize, qy as usize);
+                            let neighbour = &features[q.1 * width + q.0];
+                            let radiance = image.get(q);
+
+                            let weight = kx * ky
+                                * gaussian((compress(radiance) - color).squared_len(), sigma_color)
+                                * gaussian((neighbour.normal - center.normal).squared_len(), self.sigma_normal)
+                                * gaussian((neighbour.albedo - center.albedo).squared_len(), self.sigma_albedo)
+                                * depth_weight(center.depth, neighbour.depth, self.sigma_depth * step as f32);
+
+                            summed += radiance * weight;
+                            weights += weight;
+                        }
+                    }
+
+                    // The center pixel always weighs in
+                    filtered.set((x, y), summed / weights);
+                }
+            }
+
+            image = filtered;
+            sigma_color *= 0.5;
+        }
+
+        image
+    }
+}
+
+fn gaussian(distance_squared: f32, sigma: f32) -> f32 {
+    if sigma <= 0. {
+        return 1.
+    }
+
+    (-distance_squared / (sigma * sigma)).exp()
+}
+
+fn depth_weight(center: f32, neighbour: f32, sigma: f32) -> f32 {
+    match (center.is_finite(), neighbour.is_finite()) {
+        (true, true) => gaussian(((center - neighbour) / center.max(1e-6)).powi(2), sigma),
+        (false, false) => 1.,
+        _ => 0.,
+    }
+}
+
+// Bounds radiance so that bright pixels don't stand out from every neighbour
+fn compress(radiance: Vec3) -> Vec3 {
+    Vec3::new(
+        radiance.x() / (1. + radiance.x()),
+        radiance.y() / (1. + radiance.y()),
+        radiance.z() / (1. + radiance.z()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aov::{PixelAovs, SurfaceSample};
+    use crate::utils::{Pcg32, Rng, SeedableRng};
+
+    const SIZE: usize = 16;
+
+    // A plane facing `normal(pixel)` one unit away from every pixel
+    fn aovs(normal: impl Fn((usize, usize)) -> Vec3) -> AovBuffers {
+        let pixels = (0..SIZE)
+            .flat_map(|y| (0..SIZE).map(move |x| (x, y)))
+            .map(|pixel| {
+                let mut aovs = PixelAovs::default();
+                aovs.add(Some(SurfaceSample {
+                    albedo: Vec3::splat(0.5),
+                    normal: normal(pixel),
+                    depth: 1.,
+                    position: Vec3::splat(0.),
+                    uv: (0., 0.),
+                    material: 0,
+                    object: 0,
+                }));
+                aovs
+            })
+            .collect();
+
+        AovBuffers::from_pixels(SIZE, SIZE, pixels)
+    }
+
+    fn image(radiance: impl FnMut((usize, usize)) -> Vec3) -> Framebuffer {
+        let pixels = (0..SIZE).flat_map(|y| (0..SIZE).map(move |x| (x, y))).map(radiance).collect();
+        Framebuffer::from_pixels(SIZE, SIZE, pixels)
+    }
+
+    fn assert_flat(image: &Framebuffer, expected: Vec3) {
+        for &pixel in image.pixels() {
+            assert!((pixel - expected).len() < 1e-5, "{:?} != {:?}", pixel, expected);
+        }
+    }
+
+    #[test]
+    fn flat_images_go_through_unchanged() {
+        let color = Vec3::new(0.3, 0.4, 0.5);
+        let denoised = Denoiser::default().denoise(&image(|_| color), &aovs(|_| Vec3::new(0., 1., 0.)));
+
+        assert_flat(&denoised, color);
+    }
+
+    #[test]
+    fn zero_sigmas_leave_their_feature_out() {
+        let color = Vec3::new(0.3, 0.4, 0.5);
+        let denoiser = Denoiser { sigma_color: 0., sigma_normal: 0., sigma_depth: 0., sigma_albedo: 0., ..Denoiser::default() };
+        let denoised = denoiser.denoise(&image(|_| color), &aovs(|_| Vec3::new(0., 1., 0.)));
+
+        assert_flat(&denoised, color);
+    }
+
+    #[test]
+    fn keeps_normal_edges_and_smoothes_the_rest() {
+        // Two walls meeting down the middle of the image
+        let left = |(x, _): (usize, usize)| x < SIZE / 2;
+        let normals = aovs(|pixel| if left(pixel) { Vec3::new(1., 0., 0.) } else { Vec3::new(0., 0., 1.) });
+        let mean = |pixel| if left(pixel) { 0.2 } else { 0.8 };
+
+        let mut rng = Pcg32::seed_from_u64(0);
+        let noisy = image(|pixel| Vec3::splat(mean(pixel) + rng.gen_range(-0.1, 0.1)));
+        let denoised = Denoiser::default().denoise(&noisy, &normals);
+
+        // Mean squared distance to each wall's own radiance
+        let error = |image: &Framebuffer| {
+            let pixels = (0..SIZE).flat_map(|y| (0..SIZE).map(move |x| (x, y)));
+            pixels.map(|pixel| (image.get(pixel).x() - mean(pixel)).powi(2)).sum::<f32>() / (SIZE * SIZE) as f32
+        };
+        assert!(error(&denoised) < error(&noisy) / 4., "{} against {}", error(&denoised), error(&noisy));
+
+        for y in 0..SIZE {
+            let (inside, outside) = (denoised.get((SIZE / 2 - 1, y)).x(), denoised.get((SIZE / 2, y)).x());
+            assert!((inside - 0.2).abs() < 0.05 && (outside - 0.8).abs() < 0.05, "{} | {} on row {}", inside, outside, y);
+        }
+    }
+}
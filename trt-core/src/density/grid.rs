@@ -0,0 +1,131 @@
+use crate::prelude::Vec3;
+use super::Density;
+
+// Densities sampled at the centers of `resolution` voxels evenly filling the
+// box from `min` to `max`, trilinearly interpolated in between. Nothing
+// is outside of the box
+pub struct VoxelGrid {
+    resolution: (usize, usize, usize),
+    // x first, then y, then z
+    values: Vec<f32>,
+    min: Vec3,
+    max: Vec3,
+    max_density: f32,
+}
+
+impl VoxelGrid {
+    pub fn new(resolution: (usize, usize, usize), values: Vec<f32>, min: Vec3, max: Vec3) -> Self {
+        let (nx, ny, nz) = resolution;
+        assert!(nx > 0 && ny > 0 && nz > 0, "empty voxel grid");
+        assert_eq!(values.len(), nx * ny * nz, "voxel grid size doesn't match its resolution");
+
+        let max_density = values.iter().copied().fold(0., f32::max);
+
+        Self { resolution, values, min, max, max_density }
+    }
+
+    // Samples `density` at the center of each voxel
+    pub fn from_fn(resolution: (usize, usize, usize), min: Vec3, max: Vec3, density: impl Fn(Vec3) -> f32) -> Self {
+        let (nx, ny, nz) = resolution;
+        let voxel = (max - min) / Vec3::new(nx as f32, ny as f32, nz as f32);
+
+        let values = (0..nz)
+            .flat_map(|z| (0..ny).flat_map(move |y| (0..nx).map(move |x| (x, y, z))))
+            .map(|(x, y, z)| density(min + voxel * Vec3::new(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5)))
+            .collect();
+
+        Self::new(resolution, values, min, max)
+    }
+
+    fn value(&self, x: usize, y: usize, z: usize) -> f32 {
+        let (nx, ny, _) = self.resolution;
+        self.values[x + nx * (y + ny * z)]
+    }
+}
+
+impl Density for VoxelGrid {
+    fn density(&self, p: Vec3) -> f32 {
+        let (nx, ny, nz) = self.resolution;
+
+        let local = (p - self.min) / (self.max - self.min);
+        if local.min_element(0.) < 0. || local.max_element(1.) > 1. {
+            return 0.
+        }
+
+        // Voxel centers below `p` and how far past them it is, on each axis
+        let lower = |t: f32, n: usize| {
+            let x = (t * n as f32 - 0.5).max(0.).min((n - 1) as f32);
+            let i = (x as usize).min(n.saturating_sub(2));
+            (i, (i + 1).min(n - 1), x - i as f32)
+        };
+
+        let (x0, x1, u) = lower(local.x(), nx);
+        let (y0, y1, v) = lower(local.y(), ny);
+        let (z0, z1, w) = lower(local.z(), nz);
+
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let at_z = |z: usize| lerp(
+            lerp(self.value(x0, y0, z), self.value(x1, y0, z), u),
+            lerp(self.value(x0, y1, z), self.value(x1, y1, z), u),
+            v,
+        );
+
+        lerp(at_z(z0), at_z(z1), w)
+    }
+
+    fn max_density(&self) -> f32 {
+        self.max_density
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    // Voxels 1 unit wide from the origin, so their centers are at n + 0.5
+    fn linear() -> VoxelGrid {
+        VoxelGrid::from_fn((4, 3, 2), Vec3::splat(0.), Vec3::new(4., 3., 2.), |p| p.x() + 2. * p.y() + 3. * p.z())
+    }
+
+    #[test]
+    fn interpolates_between_centers() {
+        let grid = linear();
+
+        for &(x, y, z) in &[(0.5, 0.5, 0.5), (1.5, 2.5, 1.5), (1., 1., 1.), (2.7, 1.2, 0.9), (3.5, 2.5, 1.5)] {
+            let density = grid.density(Vec3::new(x, y, z));
+            assert!(close(density, x + 2. * y + 3. * z), "{} at {:?}", density, (x, y, z));
+        }
+        assert!(close(grid.max_density(), 3.5 + 2. * 2.5 + 3. * 1.5));
+    }
+
+    #[test]
+    fn holds_the_outer_centers_up_to_the_edges() {
+        let grid = linear();
+
+        assert!(close(grid.density(Vec3::new(0., 0., 0.)), grid.density(Vec3::new(0.5, 0.5, 0.5))));
+        assert!(close(grid.density(Vec3::new(4., 3., 2.)), grid.density(Vec3::new(3.5, 2.5, 1.5))));
+        assert!(close(grid.density(Vec3::new(0.2, 1.5, 1.9)), grid.density(Vec3::new(0.5, 1.5, 1.5))));
+    }
+
+    #[test]
+    fn empty_outside() {
+        let grid = linear();
+
+        for &(x, y, z) in &[(-0.01, 1., 1.), (4.01, 1., 1.), (1., -0.01, 1.), (1., 3.01, 1.), (1., 1., 2.01)] {
+            assert_eq!(grid.density(Vec3::new(x, y, z)), 0.);
+        }
+    }
+
+    #[test]
+    fn single_voxels_are_constant() {
+        let grid = VoxelGrid::new((1, 2, 1), vec![1., 3.], Vec3::splat(0.), Vec3::splat(1.));
+
+        assert!(close(grid.density(Vec3::new(0.1, 0.25, 0.9)), 1.));
+        assert!(close(grid.density(Vec3::new(0.9, 0.5, 0.1)), 2.));
+        assert!(close(grid.density(Vec3::new(0.5, 0.75, 0.5)), 3.));
+    }
+}
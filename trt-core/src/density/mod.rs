@@ -0,0 +1,16 @@
+use crate::prelude::Vec3;
+
+// Extinction coefficient of a heterogeneous medium throughout space
+pub trait Density {
+    fn density(&self, p: Vec3) -> f32;
+
+    // Upper bound of `density` everywhere, the majorant of delta tracking.
+    // The tighter, the fewer null collisions
+    fn max_density(&self) -> f32;
+}
+
+mod turbulence;
+pub use turbulence::Turbulence;
+
+mod grid;
+pub use grid::VoxelGrid;
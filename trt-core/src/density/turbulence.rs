@@ -0,0 +1,59 @@
+use crate::prelude::Vec3;
+use crate::perlin::Perlin;
+use crate::utils::Rng;
+use super::Density;
+
+const OCTAVES: usize = 7;
+
+// Perlin turbulence, wispy like smoke or clouds, going from 0 up to `density`
+// with features about `1 / scale` wide
+pub struct Turbulence {
+    perlin: Perlin,
+    density: f32,
+    scale: f32,
+}
+
+impl Turbulence {
+    pub fn new(density: f32, scale: f32, rng: impl Rng) -> Self {
+        Self {
+            perlin: Perlin::new(rng),
+            density,
+            scale,
+        }
+    }
+}
+
+impl Density for Turbulence {
+    fn density(&self, p: Vec3) -> f32 {
+        // Perlin noise repeats every 256 units, octaves included, but only
+        // tells positive coordinates apart
+        let p = self.scale * p;
+        let wrapped = Vec3::new(p.x().rem_euclid(256.), p.y().rem_euclid(256.), p.z().rem_euclid(256.));
+
+        self.density * self.perlin.turb(wrapped, OCTAVES).min(1.)
+    }
+
+    fn max_density(&self) -> f32 {
+        self.density
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{Pcg32, SeedableRng};
+
+    #[test]
+    fn negative_coordinates_have_their_own_noise() {
+        let turbulence = Turbulence::new(1., 1., Pcg32::seed_from_u64(0));
+
+        for i in 0..16 {
+            let p = Vec3::new(-3.3 - 0.7 * i as f32, -2.1, -5.6 + 0.4 * i as f32);
+            let density = turbulence.density(p);
+
+            // Rather than repeating every unit like the cell at the origin
+            assert!((density - turbulence.density(p - Vec3::new(1., 0., 0.))).abs() > 1e-6);
+            assert!((density - turbulence.density(p + Vec3::splat(256.))).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use crate::color::luminance;
+use crate::prelude::Vec3;
+use crate::utils::{Rng, RngCore};
+use super::{rotate_y, Environment};
+
+use std::f32::consts::PI;
+
+// Latitude-longitude HDR image wrapped around the world, its top row looking
+// straight up and its left column towards -x. Directions are sampled in
+// proportion to the luminance they receive
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+    // Around the vertical axis, in radians
+    rotation: f32,
+    intensity: f32,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    // `pixels` are linear radiances laid out row after row from the top one,
+    // the way image files store them. `rotation` is in degrees
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>, rotation: f32, intensity: f32) -> Self {
+        assert!(width > 0 && height > 0, "Empty environment map");
+        assert_eq!(pixels.len(), width * height, "Environment map and pixels dimension mismatch");
+
+        // Rows near the poles cover less solid angle
+        let weights = pixels
+            .chunks(width)
+            .enumerate()
+            .map(|(row, pixels)| {
+                let sin_theta = (PI * (row as f32 + 0.5) / height as f32).sin();
+                pixels.iter().map(|&pixel| luminance(pixel) * sin_theta).collect()
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            pixels,
+            rotation: rotation.to_radians(),
+            intensity,
+            distribution: Distribution2D::new(weights),
+        }
+    }
+
+    fn pixel(&self, (u, v): (f32, f32)) -> Vec3 {
+        let column = ((u * self.width as f32) as usize).min(self.width - 1);
+        let row = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.pixels[row * self.width + column]
+    }
+
+    // Image coordinates in [0, 1]² of a direction in the world
+    fn uv(&self, direction: Vec3) -> (f32, f32) {
+        let direction = rotate_y(direction, -self.rotation);
+        let phi = f32::atan2(direction.z(), direction.x());
+        let theta = direction.y().max(-1.).min(1.).acos();
+
+        ((phi + PI) / (2. * PI), theta / PI)
+    }
+
+    fn direction(&self, (u, v): (f32, f32)) -> Vec3 {
+        let (phi, theta) = (2. * PI * u - PI, PI * v);
+        let direction = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+        rotate_y(direction, self.rotation)
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn radiance(&self, direction: Vec3) -> Vec3 {
+        self.pixel(self.uv(direction)) * self.intensity
+    }
+
+    fn sample(&self, rng: &mut dyn RngCore) -> Option<(Vec3, f32)> {
+        let ((u, v), pdf) = self.distribution.sample(rng.gen(), rng.gen())?;
+        let direction = self.direction((u, v));
+
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0. {
+            return None
+        }
+
+        Some((direction, pdf / (2. * PI * PI * sin_theta)))
+    }
+
+    fn pdf(&self, direction: Vec3) -> f32 {
+        let (u, v) = self.uv(direction);
+
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0. {
+            return 0.
+        }
+
+        self.distribution.pdf((u, v)) / (2. * PI * PI * sin_theta)
+    }
+}
+
+// Piecewise constant density over [0, 1], following PBRT's `Distribution1D`
+struct Distribution1D {
+    function: Vec<f32>,
+    cdf: Vec<f32>,
+    integral: f32,
+}
+
+impl Distribution1D {
+    fn new(function: Vec<f32>) -> Self {
+        let n = function.len();
+        let mut cdf = vec![0.; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + function[i].max(0.) / n as f32;
+        }
+
+        let integral = cdf[n];
+        for (i, value) in cdf.iter_mut().enumerate() {
+            *value = if integral > 0. { *value / integral } else { i as f32 / n as f32 };
+        }
+
+        Self { function, cdf, integral }
+    }
+
+    // Point in [0, 1) along with its density and the segment it lies in
+    fn sample(&self, u: f32) -> (f32, f32, usize) {
+        let n = self.function.len();
+        let offset = self.cdf.partition_point(|&value| value <= u).saturating_sub(1).min(n - 1);
+
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let du = if width > 0. { (u - self.cdf[offset]) / width } else { 0. };
+
+        ((offset as f32 + du) / n as f32, self.pdf(offset), offset)
+    }
+
+    fn pdf(&self, offset: usize) -> f32 {
+        if self.integral > 0. { self.function[offset].max(0.) / self.integral } else { 1. }
+    }
+}
+
+// Density over [0, 1]², rows being picked first then a point along them
+struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    fn new(weights: Vec<Vec<f32>>) -> Self {
+        let rows = weights.into_iter().map(Distribution1D::new).collect::<Vec<_>>();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral).collect());
+
+        Self { rows, marginal }
+    }
+
+    fn sample(&self, u: f32, v: f32) -> Option<((f32, f32), f32)> {
+        if self.marginal.integral <= 0. {
+            return None
+        }
+
+        let (y, pdf_y, row) = self.marginal.sample(v);
+        let (x, pdf_x, _) = self.rows[row].sample(u);
+
+        Some(((x, y), pdf_x * pdf_y))
+    }
+
+    fn pdf(&self, (x, y): (f32, f32)) -> f32 {
+        let row = ((y * self.rows.len() as f32) as usize).min(self.rows.len() - 1);
+        let columns = self.rows[row].function.len();
+        let column = ((x * columns as f32) as usize).min(columns - 1);
+
+        self.rows[row].pdf(column) * self.marginal.pdf(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{Pcg32, SeedableRng};
+
+    const WIDTH: usize = 16;
+    const HEIGHT: usize = 8;
+
+    fn map(pixels: Vec<Vec3>, rotation: f32) -> EnvironmentMap {
+        EnvironmentMap::new(WIDTH, HEIGHT, pixels, rotation, 1.)
+    }
+
+    #[test]
+    fn sampled_densities_match_pdf() {
+        let mut rng = Pcg32::seed_from_u64(0);
+        let pixels = (0..WIDTH * HEIGHT).map(|_| Vec3::random(&mut rng) + Vec3::splat(0.01)).collect::<Vec<_>>();
+
+        for &rotation in &[0., 37.] {
+            let map = map(pixels.clone(), rotation);
+
+            for _ in 0..1000 {
+                let (direction, pdf) = map.sample(&mut rng).unwrap();
+                // Densities blow up towards the poles, where `f32` directions
+                // can't tell their elevation precisely anymore
+                if direction.y().abs() > 0.999 {
+                    continue
+                }
+
+                let expected = map.pdf(direction);
+                assert!((pdf - expected).abs() <= 1e-3 * expected, "{} != {} towards {:?}, rotated by {}", pdf, expected, direction, rotation);
+            }
+        }
+    }
+
+    #[test]
+    fn samples_land_on_bright_pixels() {
+        let bright = Vec3::splat(1000.);
+        let mut pixels = vec![Vec3::splat(0.01); WIDTH * HEIGHT];
+        pixels[3 * WIDTH + 5] = bright;
+
+        let mut rng = Pcg32::seed_from_u64(0);
+        for &rotation in &[0., 37.] {
+            let map = map(pixels.clone(), rotation);
+
+            let hits = (0..1000)
+                .filter(|_| map.sample(&mut rng).map_or(false, |(direction, _)| map.radiance(direction).x() == bright.x()))
+                .count();
+            assert!(hits > 990, "{} samples out of 1000 on the bright pixel, rotated by {}", hits, rotation);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Empty environment map")]
+    fn rejects_empty_maps() {
+        EnvironmentMap::new(0, 0, Vec::new(), 0., 1.);
+    }
+}
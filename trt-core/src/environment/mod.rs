@@ -0,0 +1,35 @@
+mod map;
+mod sky;
+
+pub use map::EnvironmentMap;
+pub use sky::Sky;
+
+use crate::prelude::Vec3;
+use crate::utils::RngCore;
+
+use std::sync::Arc;
+
+// Light arriving from infinitely far away, seen by rays escaping the world
+pub trait Environment {
+    // Radiance arriving from the unit `direction`
+    fn radiance(&self, direction: Vec3) -> Vec3;
+
+    // Unit direction towards the environment for direct lighting, along with
+    // its solid angle density
+    fn sample(&self, _rng: &mut dyn RngCore) -> Option<(Vec3, f32)> {
+        None
+    }
+
+    // Solid angle density of `sample` returning the unit `direction`
+    fn pdf(&self, _direction: Vec3) -> f32 {
+        0.
+    }
+}
+
+pub type SharedEnvironment = Arc<dyn Environment + Send + Sync>;
+
+// Rotates `direction` by `angle` radians around the vertical axis
+fn rotate_y(direction: Vec3, angle: f32) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    Vec3::new(cos * direction.x() + sin * direction.z(), direction.y(), -sin * direction.x() + cos * direction.z())
+}
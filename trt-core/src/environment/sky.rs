@@ -0,0 +1,179 @@
+use crate::color::luminance;
+use crate::prelude::Vec3;
+use crate::utils::{sample_sphere_solid_angle, Rng, RngCore};
+use super::Environment;
+
+use std::f32::consts::PI;
+
+// Apparent angular radius of the sun, in radians
+const SUN_RADIUS: f32 = 0.004_65;
+// Kilocandelas per square meter to radiance, a clear zenith being about 1
+const SKY_SCALE: f32 = 0.1;
+// Sun radiance above the atmosphere, lighting up a surface about 5 times
+// as much as the clear sky around it
+const SUN_RADIANCE: f32 = 1.5e5;
+
+// Perez distribution coefficients of luminance and chromaticities
+type Perez = [f32; 5];
+
+// Analytic daylight sky following Preetham et al. (1999), with a sun disk
+// at `elevation` degrees above the horizon and `azimuth` degrees around the
+// vertical axis from +z towards +x. Turbidity goes from 2 for a very clear
+// sky to about 10 for a hazy one. Nothing comes from below the horizon
+pub struct Sky {
+    sun_direction: Vec3,
+    intensity: f32,
+    zenith: (f32, f32, f32),
+    coefficients: [Perez; 3],
+    sun: Vec3,
+    // Odds of sampling the sun rather than the sky
+    sun_probability: f32,
+}
+
+impl Sky {
+    pub fn new(elevation: f32, azimuth: f32, turbidity: f32, intensity: f32) -> Self {
+        let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+        let sun_direction = Vec3::new(elevation.cos() * azimuth.sin(), elevation.sin(), elevation.cos() * azimuth.cos());
+        let t = turbidity;
+
+        // The model only holds for a sun above the horizon
+        let theta_sun = (PI / 2. - elevation).max(0.).min(PI / 2. - 1e-3);
+        let (t2, t3) = (theta_sun * theta_sun, theta_sun * theta_sun * theta_sun);
+
+        let chi = (4. / 9. - t / 120.) * (PI - 2. * theta_sun);
+        let zenith_luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.);
+        let zenith_x = t * t * (0.00166 * t3 - 0.00375 * t2 + 0.00209 * theta_sun)
+            + t * (-0.02903 * t3 + 0.06377 * t2 - 0.03202 * theta_sun + 0.00394)
+            + (0.11693 * t3 - 0.21196 * t2 + 0.06052 * theta_sun + 0.25886);
+        let zenith_y = t * t * (0.00275 * t3 - 0.00610 * t2 + 0.00317 * theta_sun)
+            + t * (-0.04214 * t3 + 0.08970 * t2 - 0.04153 * theta_sun + 0.00516)
+            + (0.15346 * t3 - 0.26756 * t2 + 0.06670 * theta_sun + 0.26688);
+
+        let coefficients = [
+            [0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703],
+            [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452],
+            [-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529],
+        ];
+
+        let sun = if elevation > 0. { sun_transmittance(PI / 2. - elevation, t) * SUN_RADIANCE } else { Vec3::splat(0.) };
+
+        let mut sky = Self {
+            sun_direction,
+            intensity,
+            zenith: (zenith_luminance, zenith_x, zenith_y),
+            coefficients,
+            sun,
+            sun_probability: 0.,
+        };
+
+        // Roughly in proportion to the light each brings
+        let sun_power = luminance(sky.sun) * sun_solid_angle();
+        let sky_power = luminance(sky.sky_radiance(Vec3::new(0., 1., 0.))) * 2. * PI;
+        if sun_power > 0. {
+            sky.sun_probability = (sun_power / (sun_power + sky_power)).max(0.1).min(0.9);
+        }
+
+        sky
+    }
+
+    fn sky_radiance(&self, direction: Vec3) -> Vec3 {
+        if direction.y() <= 0. {
+            return Vec3::splat(0.)
+        }
+
+        let cos_theta = direction.y();
+        let cos_gamma = Vec3::dot(direction, self.sun_direction).max(-1.).min(1.);
+        let cos_theta_sun = self.sun_direction.y().max(1e-3);
+
+        // Relative to the zenith, where the sun is theta_sun away
+        let relative = |perez: &Perez| {
+            perez_function(perez, cos_theta, cos_gamma.acos(), cos_gamma)
+                / perez_function(perez, 1., cos_theta_sun.acos(), cos_theta_sun)
+        };
+
+        let (zenith_luminance, zenith_x, zenith_y) = self.zenith;
+        let luminance = zenith_luminance * relative(&self.coefficients[0]);
+        let x = zenith_x * relative(&self.coefficients[1]);
+        let y = zenith_y * relative(&self.coefficients[2]);
+
+        xyy_to_rgb(x, y, luminance).max(Vec3::splat(0.)) * SKY_SCALE
+    }
+
+    fn in_sun(&self, direction: Vec3) -> bool {
+        Vec3::dot(direction, self.sun_direction) >= SUN_RADIUS.cos()
+    }
+}
+
+impl Environment for Sky {
+    fn radiance(&self, direction: Vec3) -> Vec3 {
+        let mut radiance = self.sky_radiance(direction);
+        if self.in_sun(direction) {
+            radiance += self.sun;
+        }
+
+        radiance * self.intensity
+    }
+
+    fn sample(&self, rng: &mut dyn RngCore) -> Option<(Vec3, f32)> {
+        let direction = if rng.gen::<f32>() < self.sun_probability {
+            sample_sphere_solid_angle(self.sun_direction, SUN_RADIUS.sin(), Vec3::splat(0.), &mut *rng)
+        } else {
+            // Uniformly over the upper hemisphere
+            let (y, phi) = (rng.gen::<f32>(), 2. * PI * rng.gen::<f32>());
+            let r = (1. - y * y).max(0.).sqrt();
+            Vec3::new(r * phi.cos(), y, r * phi.sin())
+        };
+
+        let pdf = self.pdf(direction);
+        if pdf <= 0. {
+            return None
+        }
+
+        Some((direction, pdf))
+    }
+
+    fn pdf(&self, direction: Vec3) -> f32 {
+        let sky = if direction.y() > 0. { 1. / (2. * PI) } else { 0. };
+        // Agreeing with `radiance` on where the disk ends
+        let sun = if self.in_sun(direction) { 1. / sun_solid_angle() } else { 0. };
+
+        self.sun_probability * sun + (1. - self.sun_probability) * sky
+    }
+}
+
+fn sun_solid_angle() -> f32 {
+    // 2π(1 - cos r), without cancelling out in single precision
+    4. * PI * (SUN_RADIUS / 2.).sin().powi(2)
+}
+
+fn perez_function([a, b, c, d, e]: &Perez, cos_theta: f32, gamma: f32, cos_gamma: f32) -> f32 {
+    (1. + a * (b / cos_theta.max(1e-3)).exp()) * (1. + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+fn xyy_to_rgb(x: f32, y: f32, luminance: f32) -> Vec3 {
+    if y <= 0. {
+        return Vec3::splat(0.)
+    }
+
+    let (cx, cy, cz) = (x * luminance / y, luminance, (1. - x - y) * luminance / y);
+    Vec3::new(
+        3.2406 * cx - 1.5372 * cy - 0.4986 * cz,
+        -0.9689 * cx + 1.8758 * cy + 0.0415 * cz,
+        0.0557 * cx - 0.2040 * cy + 1.0570 * cz,
+    )
+}
+
+// Share of sunlight making it through the atmosphere at each of the red,
+// green and blue wavelengths, scattered away by molecules and aerosols
+fn sun_transmittance(theta: f32, turbidity: f32) -> Vec3 {
+    let air_mass = 1. / (theta.cos() + 0.15 * (93.885 - theta.to_degrees()).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+
+    let transmittance = |wavelength: f32| {
+        let rayleigh = (-0.008735 * wavelength.powf(-4.08) * air_mass).exp();
+        let aerosol = (-beta * wavelength.powf(-1.3) * air_mass).exp();
+        rayleigh * aerosol
+    };
+
+    Vec3::new(transmittance(0.68), transmittance(0.55), transmittance(0.44))
+}
@@ -0,0 +1,202 @@
+use crate::{framebuffer::Framebuffer, prelude::Vec3};
+
+use std::f32::consts::PI;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy)]
+pub enum FilterKind {
+    Box,
+    Tent,
+    Gaussian,
+    // Mitchell-Netravali with B = C = 1/3
+    Mitchell,
+    // Sinc windowed by a sinc `radius` times as wide
+    Lanczos,
+}
+
+impl FilterKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "box" => Some(FilterKind::Box),
+            "tent" => Some(FilterKind::Tent),
+            "gaussian" => Some(FilterKind::Gaussian),
+            "mitchell" => Some(FilterKind::Mitchell),
+            "lanczos" => Some(FilterKind::Lanczos),
+            _ => None,
+        }
+    }
+
+    // Usual support of the filter, in pixels
+    pub fn default_radius(&self) -> f32 {
+        match self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell => 2.,
+            FilterKind::Lanczos => 3.,
+        }
+    }
+}
+
+// Weights samples by their distance to pixel centers, in pixels
+#[derive(Debug, Clone, Copy)]
+pub struct Filter {
+    pub kind: FilterKind,
+    pub radius: f32,
+}
+
+impl Default for Filter {
+    // Plain average of the samples within each pixel
+    fn default() -> Self {
+        Self { kind: FilterKind::Box, radius: 0.5 }
+    }
+}
+
+impl Filter {
+    pub fn evaluate(&self, dx: f32, dy: f32) -> f32 {
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+
+    fn evaluate_1d(&self, d: f32) -> f32 {
+        let (d, radius) = (d.abs(), self.radius);
+        if d >= radius {
+            return 0.
+        }
+
+        match self.kind {
+            FilterKind::Box => 1.,
+            FilterKind::Tent => radius - d,
+            FilterKind::Gaussian => {
+                const ALPHA: f32 = 2.;
+                (-ALPHA * d * d).exp() - (-ALPHA * radius * radius).exp()
+            },
+            FilterKind::Mitchell => mitchell(2. * d / radius),
+            FilterKind::Lanczos => sinc(d) * sinc(d / radius),
+        }
+    }
+}
+
+fn mitchell(x: f32) -> f32 {
+    const B: f32 = 1. / 3.;
+    const C: f32 = 1. / 3.;
+
+    let weight = if x < 1. {
+        (12. - 9. * B - 6. * C) * x * x * x + (-18. + 12. * B + 6. * C) * x * x + (6. - 2. * B)
+    } else {
+        (-B - 6. * C) * x * x * x + (6. * B + 30. * C) * x * x + (-12. * B - 48. * C) * x + (8. * B + 24. * C)
+    };
+
+    weight / 6.
+}
+
+fn sinc(x: f32) -> f32 {
+    if x < 1e-5 {
+        return 1.
+    }
+
+    (PI * x).sin() / (PI * x)
+}
+
+// Filtered sums of the samples splatted over a region of the image, each
+// sample reaching every pixel whose center is within the filter's radius
+#[derive(Debug, Clone)]
+pub struct Film {
+    filter: Filter,
+    image_size: (usize, usize),
+    xs: Range<usize>,
+    ys: Range<usize>,
+    // Weighted radiance sum and weight sum of each pixel of the region
+    pixels: Vec<(Vec3, f32)>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize, filter: Filter) -> Self {
+        Self::with_region(filter, (width, height), 0..width, 0..height)
+    }
+
+    fn with_region(filter: Filter, image_size: (usize, usize), xs: Range<usize>, ys: Range<usize>) -> Self {
+        let pixels = vec![(Vec3::splat(0.), 0.); xs.len() * ys.len()];
+        Self { filter, image_size, xs, ys, pixels }
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    // Empty film receiving the splats of samples taken in the given pixels,
+    // to render them apart and `merge` them back
+    pub fn region(&self, xs: Range<usize>, ys: Range<usize>) -> Self {
+        let padding = self.filter.radius.ceil() as usize;
+        let (width, height) = self.image_size;
+
+        let xs = xs.start.saturating_sub(padding)..(xs.end + padding).min(width);
+        let ys = ys.start.saturating_sub(padding)..(ys.end + padding).min(height);
+
+        Self::with_region(self.filter, self.image_size, xs, ys)
+    }
+
+    // `(x, y)` is a position on the image plane in pixels, pixel `(i, j)`
+    // spanning from `(i, j)` to `(i + 1, j + 1)`
+    pub fn add_sample(&mut self, (x, y): (f32, f32), radiance: Vec3) {
+        let radius = self.filter.radius;
+        let covered = |position: f32, range: &Range<usize>| {
+            let first = (position - 0.5 - radius).ceil().max(range.start as f32) as usize;
+            let last = (position - 0.5 + radius).floor().min(range.end as f32 - 1.);
+            first..(last + 1.).max(first as f32) as usize
+        };
+
+        for j in covered(y, &self.ys) {
+            for i in covered(x, &self.xs) {
+                let weight = self.filter.evaluate(x - (i as f32 + 0.5), y - (j as f32 + 0.5));
+                if weight == 0. {
+                    continue
+                }
+
+                let index = (j - self.ys.start) * self.xs.len() + (i - self.xs.start);
+                let (summed, weights) = &mut self.pixels[index];
+                *summed += radiance * weight;
+                *weights += weight;
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &Film) {
+        for (row, j) in other.ys.clone().enumerate() {
+            for (column, i) in other.xs.clone().enumerate() {
+                if !self.xs.contains(&i) || !self.ys.contains(&j) {
+                    continue
+                }
+
+                let (summed, weight) = other.pixels[row * other.xs.len() + column];
+                let pixel = &mut self.pixels[(j - self.ys.start) * self.xs.len() + (i - self.xs.start)];
+                pixel.0 += summed;
+                pixel.1 += weight;
+            }
+        }
+    }
+
+    // Filtered radiance of a pixel of the image, black without any sample
+    pub fn get(&self, (x, y): (usize, usize)) -> Vec3 {
+        let (summed, weight) = self.pixels[(y - self.ys.start) * self.xs.len() + (x - self.xs.start)];
+
+        if weight.abs() > 1e-6 {
+            summed / weight
+        } else {
+            Vec3::splat(0.)
+        }
+    }
+
+    pub fn framebuffer(&self) -> Framebuffer {
+        let pixels = self.ys
+            .clone()
+            .flat_map(|y| self.xs.clone().map(move |x| (x, y)))
+            .map(|pixel| self.get(pixel))
+            .collect();
+
+        Framebuffer::from_pixels(self.xs.len(), self.ys.len(), pixels)
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.iter_mut().for_each(|pixel| *pixel = (Vec3::splat(0.), 0.));
+    }
+}
@@ -0,0 +1,71 @@
+use crate::prelude::{Color, Vec3};
+use crate::tonemap::ToneMap;
+
+// Linear RGB radiance of a whole image, indexed with the scene's pixel
+// coordinates where `y` goes up from the bottom row
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::from_pixels(width, height, vec![Vec3::splat(0.); width * height])
+    }
+
+    // `pixels` are laid out row after row, starting from the bottom one
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Vec3>) -> Self {
+        assert_eq!(pixels.len(), width * height, "Framebuffer and pixels dimension mismatch");
+        Self { width, height, pixels }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, (x, y): (usize, usize)) -> Vec3 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, (x, y): (usize, usize), radiance: Vec3) {
+        self.pixels[y * self.width + x] = radiance;
+    }
+
+    pub fn pixels(&self) -> &[Vec3] {
+        &self.pixels
+    }
+
+    pub fn row(&self, y: usize) -> &[Vec3] {
+        &self.pixels[y * self.width..(y + 1) * self.width]
+    }
+
+    // Rows from the top one down, the way image files store them
+    pub fn image_rows(&self) -> impl Iterator<Item = &[Vec3]> {
+        self.pixels.chunks(self.width.max(1)).rev()
+    }
+
+    // Interleaved linear RGB floats, in image order
+    pub fn to_rgb_f32(&self) -> Vec<f32> {
+        self.image_rows()
+            .flatten()
+            .flat_map(|pixel| vec![pixel.x(), pixel.y(), pixel.z()])
+            .collect()
+    }
+
+    // Interleaved tone mapped and gamma corrected 8-bit RGB, in image order
+    pub fn to_rgb8(&self, tone_map: ToneMap) -> Vec<u8> {
+        self.image_rows()
+            .flatten()
+            .flat_map(|&pixel| {
+                let Color(r, g, b) = Color::from_linear(tone_map.apply(pixel));
+                vec![r, g, b]
+            })
+            .collect()
+    }
+}
@@ -1,5 +1,6 @@
 use crate::prelude::{Hit, Material, MaterialBuilder, HitRecord, AABB, Ray, Vec3};
-use crate::hit::RectBuilder;
+use crate::hit::{object_id, RectBuilder};
+use crate::utils::RngCore;
 
 pub struct HitBox<T> {
     pmin: Vec3,
@@ -36,8 +37,10 @@ impl<T: Material + Clone> HitBox<T> {
 }
 
 impl<T: Material> Hit for HitBox<T> {
+    // The faces are a single object
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
-        self.list.hit(ray, t_min, t_max)
+        let rec = self.list.hit(ray, t_min, t_max)?;
+        Some(HitRecord { object: object_id(self), ..rec })
     }
 
     fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
@@ -46,4 +49,24 @@ impl<T: Material> Hit for HitBox<T> {
             max: self.pmax,
         })
     }
+
+    fn emitter_count(&self) -> usize {
+        self.list.emitter_count()
+    }
+
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        self.list.emitter_pdf(origin, direction, time)
+    }
+
+    fn sample_emitter(&self, origin: Vec3, time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        self.list.sample_emitter(origin, time, rng)
+    }
+
+    fn sample_emitter_surface(&self, time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        self.list.sample_emitter_surface(time, rng)
+    }
+
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        self.list.emitter_surface_pdf(ray, t)
+    }
 }
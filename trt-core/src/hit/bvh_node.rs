@@ -1,18 +1,21 @@
-use crate::prelude::{Hit, AABB, HitRecord, Ray};
+use crate::prelude::{Hit, AABB, HitRecord, Ray, Vec3};
 
-use crate::utils::{thread_rng, SliceRandom};
+use crate::utils::{pick, Rng, RngCore, SliceRandom};
 use std::cmp::Ordering;
 
 pub struct BVHNode<T: Hit> {
     left: HitNode<T>,
     right: HitNode<T>,
     bbox: AABB,
+    emitters: usize,
+    // A single hittable is put on both sides
+    duplicated: bool,
 }
 
 impl<T: Hit + Clone> BVHNode<T> {
-    pub fn new(hittables: &mut [T], time0: f32, time1: f32) -> Self {
+    pub fn new(hittables: &mut [T], time0: f32, time1: f32, mut rng: impl Rng) -> Self {
         use HitNode::{BVH, Direct};
-        let compare = [box_x_cmp, box_y_cmp, box_z_cmp].choose(&mut thread_rng()).unwrap();
+        let compare = [box_x_cmp, box_y_cmp, box_z_cmp].choose(&mut rng).unwrap();
 
         hittables.sort_by(|a, b| compare(a, b));
 
@@ -20,10 +23,13 @@ impl<T: Hit + Clone> BVHNode<T> {
             1 => (Direct(hittables[0].clone()), Direct(hittables[0].clone())),
             2 => (Direct(hittables[0].clone()), Direct(hittables[1].clone())),
             n => {
+                // Through a trait object, or every level would instantiate
+                // `new` for yet another reference to the generator
+                let rng: &mut dyn RngCore = &mut rng;
                 let (left_l, right_l) = hittables.split_at_mut(n / 2);
                 (
-                    BVH(Box::new(BVHNode::new(left_l, time0, time1))),
-                    BVH(Box::new(BVHNode::new(right_l, time0, time1))),
+                    BVH(Box::new(BVHNode::new(left_l, time0, time1, &mut *rng))),
+                    BVH(Box::new(BVHNode::new(right_l, time0, time1, rng))),
                 )
             }
         };
@@ -31,14 +37,26 @@ impl<T: Hit + Clone> BVHNode<T> {
         let box_left = left.bounding_box(time0, time1).expect("missing bbox in BVH::new");
         let box_right = right.bounding_box(time0, time1).expect("missing bbox in BVH::new");
 
+        // Counted once, so that sampling and densities agree
+        let duplicated = hittables.len() == 1;
+        let emitters = left.emitter_count() + if duplicated { 0 } else { right.emitter_count() };
+
         Self {
             left,
             right,
             bbox: AABB::surrounding_box(box_left, box_right),
+            emitters,
+            duplicated,
         }
     }
 }
 
+impl<T: Hit> BVHNode<T> {
+    fn right_emitter_count(&self) -> usize {
+        if self.duplicated { 0 } else { self.right.emitter_count() }
+    }
+}
+
 impl<T: Hit> Hit for BVHNode<T> {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         if self.bbox.hit(ray, t_min, t_max) {
@@ -61,6 +79,79 @@ impl<T: Hit> Hit for BVHNode<T> {
     fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
         Some(self.bbox.clone())
     }
+
+    fn emitter_count(&self) -> usize {
+        self.emitters
+    }
+
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        if self.emitters == 0 {
+            return 0.
+        }
+
+        let (count_left, count_right) = (self.left.emitter_count(), self.right_emitter_count());
+        let mut summed_pdf = 0.;
+
+        if count_left > 0 {
+            summed_pdf += count_left as f32 * self.left.emitter_pdf(origin, direction, time);
+        }
+        if count_right > 0 {
+            summed_pdf += count_right as f32 * self.right.emitter_pdf(origin, direction, time);
+        }
+
+        summed_pdf / self.emitters as f32
+    }
+
+    fn sample_emitter(&self, origin: Vec3, time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        if pick(&mut *rng, self.emitters) < self.left.emitter_count() {
+            self.left.sample_emitter(origin, time, rng)
+        } else {
+            self.right.sample_emitter(origin, time, rng)
+        }
+    }
+
+    fn sample_emitter_surface(&self, time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        let count_left = self.left.emitter_count();
+        let (picked, count) = if pick(&mut *rng, self.emitters) < count_left {
+            (self.left.sample_emitter_surface(time, rng), count_left)
+        } else {
+            (self.right.sample_emitter_surface(time, rng), self.emitters - count_left)
+        };
+
+        let (rec, pdf) = picked?;
+        Some((rec, pdf * count as f32 / self.emitters as f32))
+    }
+
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        if self.emitters == 0 {
+            return 0.
+        }
+
+        let (count_left, count_right) = (self.left.emitter_count(), self.right_emitter_count());
+        let mut summed_pdf = 0.;
+
+        if count_left > 0 {
+            summed_pdf += count_left as f32 * self.left.emitter_surface_pdf(ray, t);
+        }
+        if count_right > 0 {
+            summed_pdf += count_right as f32 * self.right.emitter_surface_pdf(ray, t);
+        }
+
+        summed_pdf / self.emitters as f32
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        if !self.bbox.hit(ray, t_min, t_max) {
+            return 1.
+        }
+
+        let transmittance = self.left.transmittance(ray, t_min, t_max);
+        if transmittance <= 0. || self.duplicated {
+            return transmittance
+        }
+
+        transmittance * self.right.transmittance(ray, t_min, t_max)
+    }
 }
 
 enum HitNode<T: Hit> {
@@ -82,6 +173,48 @@ impl<T: Hit> Hit for HitNode<T> {
             HitNode::Direct(h) => h.bounding_box(t0, t1),
         }
     }
+
+    fn emitter_count(&self) -> usize {
+        match self {
+            HitNode::BVH(node) => node.emitter_count(),
+            HitNode::Direct(h) => h.emitter_count(),
+        }
+    }
+
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        match self {
+            HitNode::BVH(node) => node.emitter_pdf(origin, direction, time),
+            HitNode::Direct(h) => h.emitter_pdf(origin, direction, time),
+        }
+    }
+
+    fn sample_emitter(&self, origin: Vec3, time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        match self {
+            HitNode::BVH(node) => node.sample_emitter(origin, time, rng),
+            HitNode::Direct(h) => h.sample_emitter(origin, time, rng),
+        }
+    }
+
+    fn sample_emitter_surface(&self, time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        match self {
+            HitNode::BVH(node) => node.sample_emitter_surface(time, rng),
+            HitNode::Direct(h) => h.sample_emitter_surface(time, rng),
+        }
+    }
+
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        match self {
+            HitNode::BVH(node) => node.emitter_surface_pdf(ray, t),
+            HitNode::Direct(h) => h.emitter_surface_pdf(ray, t),
+        }
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        match self {
+            HitNode::BVH(node) => node.transmittance(ray, t_min, t_max),
+            HitNode::Direct(h) => h.transmittance(ray, t_min, t_max),
+        }
+    }
 }
 
 fn box_x_cmp(ah: &dyn Hit, bh: &dyn Hit) -> Ordering {
@@ -107,3 +240,36 @@ fn box_z_cmp(ah: &dyn Hit, bh: &dyn Hit) -> Ordering {
     PartialOrd::partial_cmp(&box_left.min.z(), &box_right.min.z())
         .expect("got NaNs")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hit::Sphere;
+    use crate::material::MaterialBuilderExt;
+    use crate::utils::{Pcg32, SeedableRng};
+
+    use std::f32::consts::PI;
+    use std::sync::Arc;
+
+    #[test]
+    fn duplicated_emitters_are_counted_once() {
+        // Split into a single light, which ends up on both sides, and two lights
+        let mut lights = (0..3)
+            .map(|i| Arc::new(Sphere::builder().center((4 * i, 0, 0)).radius(1).diffuse_color((1, 1, 1))))
+            .collect::<Vec<_>>();
+        let bvh = BVHNode::new(&mut lights, 0., 1., Pcg32::seed_from_u64(0));
+        assert_eq!(bvh.emitter_count(), 3);
+
+        let expected = 1. / (3. * 4. * PI);
+        let mut rng = Pcg32::seed_from_u64(1);
+
+        for _ in 0..100 {
+            let (rec, pdf) = bvh.sample_emitter_surface(0., &mut rng).unwrap();
+            assert!((pdf - expected).abs() < 1e-6, "{} != {}", pdf, expected);
+
+            let ray = Ray { origin: rec.p + rec.normal, direction: -rec.normal, time: 0., wavelength: None, seed: 0 };
+            let surface_pdf = bvh.emitter_surface_pdf(&ray, 1.);
+            assert!((surface_pdf - expected).abs() < 1e-6, "{} != {}", surface_pdf, expected);
+        }
+    }
+}
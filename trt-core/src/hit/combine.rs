@@ -1,13 +1,16 @@
-use crate::prelude::{Hit, AABB, HitRecord, Ray};
+use crate::prelude::{Hit, AABB, HitRecord, Ray, Vec3};
+use crate::utils::{pick, RngCore};
 
 pub struct Combine<T: Hit, U: Hit> {
     a: T,
     b: U,
+    emitters: usize,
 }
 
 impl<T: Hit, U: Hit> Combine<T, U> {
     pub fn new(a: T, b: U) -> Self {
-        Self { a, b }
+        let emitters = a.emitter_count() + b.emitter_count();
+        Self { a, b, emitters }
     }
 }
 
@@ -25,4 +28,73 @@ impl<T: Hit, U: Hit> Hit for Combine<T, U> {
 
         Some(AABB::surrounding_box(bbox_a, bbox_b))
     }
+
+    fn emitter_count(&self) -> usize {
+        self.emitters
+    }
+
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        if self.emitters == 0 {
+            return 0.
+        }
+
+        let (count_a, count_b) = (self.a.emitter_count(), self.b.emitter_count());
+        let mut summed_pdf = 0.;
+
+        if count_a > 0 {
+            summed_pdf += count_a as f32 * self.a.emitter_pdf(origin, direction, time);
+        }
+        if count_b > 0 {
+            summed_pdf += count_b as f32 * self.b.emitter_pdf(origin, direction, time);
+        }
+
+        summed_pdf / self.emitters as f32
+    }
+
+    fn sample_emitter(&self, origin: Vec3, time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        if pick(&mut *rng, self.emitters) < self.a.emitter_count() {
+            self.a.sample_emitter(origin, time, rng)
+        } else {
+            self.b.sample_emitter(origin, time, rng)
+        }
+    }
+
+    fn sample_emitter_surface(&self, time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        let count_a = self.a.emitter_count();
+        let (picked, count) = if pick(&mut *rng, self.emitters) < count_a {
+            (self.a.sample_emitter_surface(time, rng), count_a)
+        } else {
+            (self.b.sample_emitter_surface(time, rng), self.emitters - count_a)
+        };
+
+        let (rec, pdf) = picked?;
+        Some((rec, pdf * count as f32 / self.emitters as f32))
+    }
+
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        if self.emitters == 0 {
+            return 0.
+        }
+
+        let (count_a, count_b) = (self.a.emitter_count(), self.b.emitter_count());
+        let mut summed_pdf = 0.;
+
+        if count_a > 0 {
+            summed_pdf += count_a as f32 * self.a.emitter_surface_pdf(ray, t);
+        }
+        if count_b > 0 {
+            summed_pdf += count_b as f32 * self.b.emitter_surface_pdf(ray, t);
+        }
+
+        summed_pdf / self.emitters as f32
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let transmittance = self.a.transmittance(ray, t_min, t_max);
+        if transmittance <= 0. {
+            return 0.
+        }
+
+        transmittance * self.b.transmittance(ray, t_min, t_max)
+    }
 }
@@ -1,5 +1,7 @@
 use crate::prelude::{Material, Texture, Hit, AABB, HitRecord, Ray, Vec3};
+use crate::hit::object_id;
 use crate::material::Isotropic;
+use crate::utils::{ray_rng, Rng};
 
 pub struct ConstantMedium<T: Hit, Mat: Material> {
     boundary: T,
@@ -21,28 +23,26 @@ impl<T: Hit, Tx: Texture> ConstantMedium<T, Isotropic<Tx>> {
 
 impl<T: Hit, Mat: Material> Hit for ConstantMedium<T, Mat> {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
-        let f_max = std::f32::MAX;
+        let mut intervals = Inside::new(&self.boundary, ray, t_min, t_max);
+        let (mut t_enter, mut t_exit) = intervals.next()?;
 
-        let mut rec1 = self.boundary.hit(ray, -f_max, f_max)?;
-        let mut rec2 = self.boundary.hit(ray, rec1.t + 0.0001, f_max)?;
+        // Salted with the entry point so that overlapping media are independent
+        let mut hit_distance = -(1. / self.density) * ray_rng(ray, t_enter).gen::<f32>().ln();
 
-        if rec1.t < t_min { rec1.t = t_min }
-        if rec2.t > t_max { rec2.t = t_max }
+        // Spent across the stretches inside the boundary until it runs out
+        loop {
+            let distance_inside_boundary = (t_exit - t_enter) * ray.direction.len();
+            if hit_distance < distance_inside_boundary {
+                break
+            }
 
-        if rec1.t >= rec2.t {
-            return None
-        }
-
-        if rec1.t < 0. { rec1.t = 0. }
-
-        let distance_inside_boundary = (rec2.t - rec1.t) * ray.direction.len();
-        let hit_distance = -(1. / self.density) * rand::random::<f32>().ln();
-
-        if hit_distance >= distance_inside_boundary {
-            return None
+            hit_distance -= distance_inside_boundary;
+            let (next_enter, next_exit) = intervals.next()?;
+            t_enter = next_enter;
+            t_exit = next_exit;
         }
 
-        let t = rec1.t + hit_distance / ray.direction.len();
+        let t = t_enter + hit_distance / ray.direction.len();
 
         Some(HitRecord {
             t,
@@ -51,10 +51,206 @@ impl<T: Hit, Mat: Material> Hit for ConstantMedium<T, Mat> {
             mat: &self.phase_function,
             u: 0.,
             v: 0.,
+            object: object_id(self),
         })
     }
 
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
         self.boundary.bounding_box(t0, t1)
     }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let distance_inside_boundary = Inside::new(&self.boundary, ray, t_min, t_max)
+            .map(|(t_enter, t_exit)| (t_exit - t_enter) * ray.direction.len())
+            .sum::<f32>();
+
+        (-self.density * distance_inside_boundary).exp()
+    }
+}
+
+// Stretches of `ray` between `t_min` and `t_max` inside a closed boundary,
+// whatever its shape, however many pieces it is made of and wherever the ray
+// starts. Crossings are walked in order from far behind the origin, going in
+// or out by the boundary's outward normals, so that overlapping pieces make
+// up their union
+pub(super) struct Inside<'a, T: Hit> {
+    boundary: &'a T,
+    ray: &'a Ray,
+    t_min: f32,
+    t_max: f32,
+    // Where to look for the next crossing from
+    t: f32,
+}
+
+impl<'a, T: Hit> Inside<'a, T> {
+    pub(super) fn new(boundary: &'a T, ray: &'a Ray, t_min: f32, t_max: f32) -> Self {
+        Self { boundary, ray, t_min, t_max, t: -std::f32::MAX }
+    }
+
+    // Next crossing along the ray, and whether it goes in
+    fn crossing(&mut self) -> Option<(f32, bool)> {
+        let rec = self.boundary.hit(self.ray, self.t, std::f32::MAX)?;
+
+        // Past it by a few ulps, so that it isn't found again. A fixed step
+        // gets lost in rounding far along the ray, a large one skips thin pieces
+        let t = rec.t.max(self.t);
+        let next = t + t.abs().max(1.) * 4. * std::f32::EPSILON;
+        if !(next > self.t) {
+            return None
+        }
+        self.t = next;
+
+        Some((rec.t, Vec3::dot(rec.normal, self.ray.direction) < 0.))
+    }
+}
+
+impl<'a, T: Hit> Iterator for Inside<'a, T> {
+    type Item = (f32, f32);
+
+    fn next(&mut self) -> Option<(f32, f32)> {
+        loop {
+            let t_enter = loop {
+                if self.t >= self.t_max {
+                    return None
+                }
+
+                // Leaving without having come in only happens with open boundaries
+                match self.crossing()? {
+                    (t, true) => break t,
+                    (_, false) => continue,
+                }
+            };
+
+            // Pieces of the boundary the ray is in
+            let mut depth = 1;
+            let t_exit = loop {
+                match self.crossing() {
+                    Some((t, _)) if t >= self.t_max => break t,
+                    Some((_, true)) => depth += 1,
+                    Some((t, false)) => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break t
+                        }
+                    },
+                    None => {
+                        self.t = std::f32::MAX;
+                        break std::f32::MAX
+                    },
+                }
+            };
+
+            let (t_enter, t_exit) = (t_enter.max(self.t_min), t_exit.min(self.t_max));
+            if t_enter < t_exit {
+                return Some((t_enter, t_exit))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hit::{BVHNode, HitBox, HitList, RectBuilder, Sphere};
+    use crate::material::{Lambertian, MaterialBuilder};
+    use crate::texture::Constant;
+    use crate::utils::{Pcg32, SeedableRng};
+
+    use std::sync::Arc;
+
+    fn sphere(center: (f32, f32, f32), radius: f32) -> Sphere<Lambertian<Constant>> {
+        Sphere::builder().center(center).radius(radius).material(Lambertian::colored((0.5, 0.5, 0.5)))
+    }
+
+    fn along_x(x: f32) -> Ray {
+        Ray { origin: Vec3::new(x, 0., 0.), direction: Vec3::new(1., 0., 0.), time: 0., wavelength: None, seed: 0 }
+    }
+
+    fn assert_intervals(boundary: &impl Hit, ray: &Ray, t_max: f32, expected: &[(f32, f32)]) {
+        let intervals = Inside::new(boundary, ray, 0.001, t_max).collect::<Vec<_>>();
+        let close = |a: f32, b: f32| (a - b).abs() <= 1e-3 * b.abs().max(1.);
+
+        assert_eq!(intervals.len(), expected.len(), "{:?} != {:?}", intervals, expected);
+        for (&(t_enter, t_exit), &(expected_enter, expected_exit)) in intervals.iter().zip(expected) {
+            assert!(close(t_enter, expected_enter) && close(t_exit, expected_exit), "{:?} != {:?}", intervals, expected);
+        }
+    }
+
+    #[test]
+    fn ray_starting_inside() {
+        assert_intervals(&sphere((0., 0., 0.), 1.), &along_x(0.5), std::f32::MAX, &[(0.001, 0.5)]);
+    }
+
+    #[test]
+    fn concave_boundary() {
+        // Spherical shell, the hollow's normals pointing inwards
+        let shell = HitList::new(vec![
+            Arc::new(sphere((0., 0., 0.), 2.)) as Arc<dyn Hit>,
+            Arc::new(sphere((0., 0., 0.), 1.).flip_normals()),
+        ]);
+
+        assert_intervals(&shell, &along_x(-5.), std::f32::MAX, &[(3., 4.), (6., 7.)]);
+        assert_intervals(&shell, &along_x(0.), std::f32::MAX, &[(1., 2.)]);
+    }
+
+    #[test]
+    fn overlapping_spheres() {
+        let union = HitList::new(vec![sphere((-0.5, 0., 0.), 1.), sphere((0.5, 0., 0.), 1.)]);
+
+        assert_intervals(&union, &along_x(-5.), std::f32::MAX, &[(3.5, 6.5)]);
+        assert_intervals(&union, &along_x(0.), std::f32::MAX, &[(0.001, 1.5)]);
+    }
+
+    #[test]
+    fn far_away_box() {
+        let material = Arc::new(Lambertian::colored((0.5, 0.5, 0.5)));
+        let boundary = HitBox::new(Vec3::new(5000., -1., -1.), Vec3::new(5002., 1., 1.), material);
+
+        assert_intervals(&boundary, &along_x(0.), std::f32::MAX, &[(5000., 5002.)]);
+        assert_intervals(&boundary, &along_x(-1e5), std::f32::MAX, &[(105_000., 105_002.)]);
+        assert_intervals(&boundary, &along_x(0.), 5001., &[(5000., 5001.)]);
+    }
+
+    #[test]
+    fn rotated_box() {
+        let material = Arc::new(Lambertian::colored((0.5, 0.5, 0.5)));
+        let boundary = HitBox::new(Vec3::splat(-0.5), Vec3::splat(0.5), material).rotate_y(45.);
+        let half_diagonal = 0.5 * 2f32.sqrt();
+
+        assert_intervals(&boundary, &along_x(-5.), std::f32::MAX, &[(5. - half_diagonal, 5. + half_diagonal)]);
+        assert_intervals(&boundary, &along_x(0.), std::f32::MAX, &[(0.001, half_diagonal)]);
+    }
+
+    #[test]
+    fn bvh_of_spheres() {
+        let mut spheres = (0..5).map(|i| Arc::new(sphere((4. * i as f32, 0., 0.), 1.))).collect::<Vec<_>>();
+        let boundary = BVHNode::new(&mut spheres, 0., 1., Pcg32::seed_from_u64(0));
+
+        let expected = (0..5).map(|i| (4. + 4. * i as f32, 6. + 4. * i as f32)).collect::<Vec<_>>();
+        assert_intervals(&boundary, &along_x(-5.), std::f32::MAX, &expected);
+        assert_intervals(&boundary, &along_x(2.), 10., &[(1., 3.), (5., 7.), (9., 10.)]);
+    }
+
+    #[test]
+    fn open_boundary() {
+        // Its normal points along +x: going through it against the normal is
+        // going in, and nothing ever leads back out
+        let wall = RectBuilder.y(-1..=1).z(-1..=1).x(2).material(Lambertian::colored((0.5, 0.5, 0.5)));
+        let backwards = Ray { origin: Vec3::new(5., 0., 0.), direction: Vec3::new(-1., 0., 0.), time: 0., wavelength: None, seed: 0 };
+
+        assert_intervals(&wall, &backwards, 10., &[(3., 10.)]);
+        assert_intervals(&wall, &along_x(0.), 10., &[]);
+    }
+
+    #[test]
+    fn collisions_follow_the_seed_of_the_path() {
+        let medium = ConstantMedium::new_iso(sphere((0., 0., 0.), 100.), 1., Constant::new(Vec3::splat(1.)));
+        let collision = |seed| {
+            let ray = Ray { seed, ..along_x(-200.) };
+            medium.hit(&ray, 0.001, std::f32::MAX).unwrap().t
+        };
+
+        assert_eq!(collision(1), collision(1));
+        assert!((1..8).map(collision).any(|t| t != collision(0)));
+    }
 }
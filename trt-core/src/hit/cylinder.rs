@@ -1,5 +1,8 @@
 use crate::prelude::{Material, Hit, AABB, HitRecord, Ray, Vec3, Asf32};
-use crate::{utils::cylinder_uv, material::MaterialBuilder};
+use crate::hit::object_id;
+use crate::debug::count_primitive_test;
+use crate::{utils::{cylinder_uv, sample_sphere_solid_angle, sphere_solid_angle_pdf, same_hit, Rng, RngCore}, material::MaterialBuilder};
+use std::f32::consts::PI;
 
 pub struct Cylinder<Mat> {
     base: Vec3,
@@ -16,8 +19,24 @@ impl Cylinder<UnboundedMat> {
     }
 }
 
+impl<Mat> Cylinder<Mat> {
+    // Emitters are sampled through their bounding sphere
+    fn bounding_sphere(&self) -> (Vec3, f32) {
+        let half_height = self.height / 2.;
+        let center = self.base + Vec3::new(0, half_height, 0);
+        let radius = (self.radius * self.radius + half_height * half_height).sqrt();
+        (center, radius)
+    }
+
+    fn area(&self) -> f32 {
+        2. * PI * self.radius * (self.radius + self.height)
+    }
+}
+
 impl<Mat: Material> Hit for Cylinder<Mat> {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        count_primitive_test();
+
         // let ca = self.pmax - self.pmin;
         // let center = (self.pmax - self.pmin) / 2.;
         let oc = ray.origin - self.base;
@@ -51,31 +70,44 @@ impl<Mat: Material> Hit for Cylinder<Mat> {
             let ycap = (self.height, 0.);
             let cap = ((ycap.0 - oc.y()) / ray.direction.y(), (ycap.1 - oc.y()) / ray.direction.y());
 
-            let mut capped = false;
-            let mut cap_neg = false;
+            // Which cap, if any, each end of the segment inside is on
+            let mut near_cap = None;
             if ynear < ycap.1 {
                 near = cap.1;
-                capped = true;
-                cap_neg = true;
+                near_cap = Some(-1.);
             } else if ynear > ycap.0 {
                 near = cap.0;
-                capped = true;
+                near_cap = Some(1.);
             }
 
+            let mut far_cap = None;
             if yfar < ycap.1 {
-                far = cap.1
+                far = cap.1;
+                far_cap = Some(-1.);
             } else if yfar > ycap.0 {
-                far = cap.0
+                far = cap.0;
+                far_cap = Some(1.);
             }
 
-            if far > 0. && far > near && far < t_max && far > t_min {
-                let t = near;
+            // The near end, or the far one when starting inside
+            let hit = if far <= near {
+                None
+            } else if near > t_min && near < t_max {
+                Some((near, near_cap))
+            } else if far > t_min && far < t_max {
+                Some((far, far_cap))
+            } else {
+                None
+            };
+
+            if let Some((t, cap)) = hit {
                 let p = ray.point_at_parameter(t);
-                let normal = if capped {
-                    Vec3::new(0, if cap_neg { -1. } else { 1. }, 0)
-                } else {
-                    let centered = p - self.base;
-                    Vec3::new(centered.x(), 0., centered.z()) / self.radius
+                let normal = match cap {
+                    Some(side) => Vec3::new(0., side, 0.),
+                    None => {
+                        let centered = p - self.base;
+                        Vec3::new(centered.x(), 0., centered.z()) / self.radius
+                    },
                 };
                 let (u, v) = cylinder_uv(p);
                 return Some(HitRecord {
@@ -83,7 +115,8 @@ impl<Mat: Material> Hit for Cylinder<Mat> {
                     p,
                     normal,
                     mat: &self.material,
-                    u, v
+                    u, v,
+                    object: object_id(self),
                 })
             }
         }
@@ -98,6 +131,51 @@ impl<Mat: Material> Hit for Cylinder<Mat> {
             max: self.base + Vec3::new(r, self.height, r),
         })
     }
+
+    fn emitter_count(&self) -> usize {
+        self.material.is_emissive() as usize
+    }
+
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, _time: f32) -> f32 {
+        let (center, radius) = self.bounding_sphere();
+        sphere_solid_angle_pdf(center, radius, origin, direction)
+    }
+
+    fn sample_emitter(&self, origin: Vec3, _time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        let (center, radius) = self.bounding_sphere();
+        sample_sphere_solid_angle(center, radius, origin, rng)
+    }
+
+    fn sample_emitter_surface(&self, _time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        if !self.material.is_emissive() {
+            return None
+        }
+
+        // Pick the side or one of the caps proportionally to their area
+        let phi = 2. * PI * rng.gen::<f32>();
+        let side = (phi.cos(), phi.sin());
+        let (p, normal) = if rng.gen::<f32>() * (self.radius + self.height) < self.height {
+            let y = self.height * rng.gen::<f32>();
+            let normal = Vec3::new(side.0, 0, side.1);
+            (self.base + Vec3::new(0, y, 0) + self.radius * normal, normal)
+        } else {
+            let r = self.radius * rng.gen::<f32>().sqrt();
+            let (y, normal_y) = if rng.gen::<bool>() { (self.height, 1.) } else { (0., -1.) };
+            (self.base + Vec3::new(r * side.0, y, r * side.1), Vec3::new(0, normal_y, 0))
+        };
+
+        let (u, v) = cylinder_uv(p);
+        let rec = HitRecord { t: 0., p, normal, mat: &self.material, u, v, object: object_id(self) };
+
+        Some((rec, 1. / self.area()))
+    }
+
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        match self.hit(ray, 0.001, std::f32::MAX) {
+            Some(rec) if same_hit(rec.t, t) => 1. / self.area(),
+            _ => 0.,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
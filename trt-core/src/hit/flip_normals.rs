@@ -1,4 +1,5 @@
-use crate::prelude::{Hit, AABB, HitRecord, Ray};
+use crate::prelude::{Hit, AABB, HitRecord, Ray, Vec3};
+use crate::utils::RngCore;
 
 pub struct FlipNormals<T: Hit> {
     wrapped: T,
@@ -20,4 +21,30 @@ impl<T: Hit> Hit for FlipNormals<T> {
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
         self.wrapped.bounding_box(t0, t1)
     }
+
+    fn emitter_count(&self) -> usize {
+        self.wrapped.emitter_count()
+    }
+
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        self.wrapped.emitter_pdf(origin, direction, time)
+    }
+
+    fn sample_emitter(&self, origin: Vec3, time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        self.wrapped.sample_emitter(origin, time, rng)
+    }
+
+    fn sample_emitter_surface(&self, time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        let (mut rec, pdf) = self.wrapped.sample_emitter_surface(time, rng)?;
+        rec.normal = -rec.normal;
+        Some((rec, pdf))
+    }
+
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        self.wrapped.emitter_surface_pdf(ray, t)
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        self.wrapped.transmittance(ray, t_min, t_max)
+    }
 }
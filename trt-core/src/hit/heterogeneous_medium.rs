@@ -0,0 +1,100 @@
+use crate::prelude::{Material, Texture, Hit, AABB, HitRecord, Ray, Vec3};
+use crate::density::Density;
+use crate::hit::object_id;
+use crate::material::Isotropic;
+use crate::utils::{ray_rng, Rng};
+use super::constant_medium::Inside;
+
+// Participating medium inside `boundary` whose density varies from point to
+// point. Free flights are sampled by delta tracking against the density's
+// majorant, and transmittance is estimated by ratio tracking
+pub struct HeterogeneousMedium<T: Hit, D: Density, Mat: Material> {
+    boundary: T,
+    density: D,
+    phase_function: Mat,
+}
+
+impl<T: Hit, D: Density, Mat: Material> HeterogeneousMedium<T, D, Mat> {
+    pub fn new(boundary: T, density: D, phase_function: Mat) -> Self {
+        Self { boundary, density, phase_function }
+    }
+
+    // Tentative collisions between `t_enter` and `t_exit`, null ones
+    // included, at exponentially distributed distances as if the medium was
+    // as dense as its majorant everywhere
+    fn collisions<'a, R: Rng>(&self, ray: &Ray, t_enter: f32, t_exit: f32, rng: &'a mut R) -> impl Iterator<Item = f32> + 'a {
+        let step = 1. / (self.density.max_density() * ray.direction.len());
+        let mut t = t_enter;
+
+        std::iter::from_fn(move || {
+            t -= step * rng.gen::<f32>().ln();
+            Some(t)
+        })
+        .take_while(move |&t| t < t_exit)
+    }
+}
+
+impl<T: Hit, Tx: Texture, D: Density> HeterogeneousMedium<T, D, Isotropic<Tx>> {
+    pub fn new_iso(boundary: T, density: D, texture: Tx) -> Self {
+        Self::new(boundary, density, Isotropic::new(texture))
+    }
+}
+
+impl<T: Hit, D: Density, Mat: Material> Hit for HeterogeneousMedium<T, D, Mat> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        if self.density.max_density() <= 0. {
+            return None
+        }
+
+        let mut intervals = Inside::new(&self.boundary, ray, t_min, t_max);
+        let (t_enter, t_exit) = intervals.next()?;
+        // Salted with the entry point so that overlapping media are independent,
+        // with an own stream for telling real collisions from null ones
+        let (mut rng, mut accept_rng) = (ray_rng(ray, t_enter), ray_rng(ray, t_exit));
+
+        // Free flights start over in each stretch inside the boundary
+        let t = std::iter::once((t_enter, t_exit))
+            .chain(intervals)
+            .find_map(|(t_enter, t_exit)| {
+                self.collisions(ray, t_enter, t_exit, &mut rng)
+                    .find(|&t| accept_rng.gen::<f32>() * self.density.max_density() < self.density.density(ray.point_at_parameter(t)))
+            })?;
+
+        Some(HitRecord {
+            t,
+            p: ray.point_at_parameter(t),
+            normal: Vec3::new(1., 0., 0.),
+            mat: &self.phase_function,
+            u: 0.,
+            v: 0.,
+            object: object_id(self),
+        })
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.boundary.bounding_box(t0, t1)
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let majorant = self.density.max_density();
+        if majorant <= 0. {
+            return 1.
+        }
+
+        let mut intervals = Inside::new(&self.boundary, ray, t_min, t_max);
+        let (t_enter, t_exit) = match intervals.next() {
+            Some(interval) => interval,
+            None => return 1.,
+        };
+        let mut rng = ray_rng(ray, t_enter);
+
+        std::iter::once((t_enter, t_exit))
+            .chain(intervals)
+            .map(|(t_enter, t_exit)| {
+                self.collisions(ray, t_enter, t_exit, &mut rng)
+                    .map(|t| 1. - self.density.density(ray.point_at_parameter(t)) / majorant)
+                    .product::<f32>()
+            })
+            .product()
+    }
+}
@@ -1,10 +1,16 @@
-use crate::prelude::{Hit, AABB, HitRecord, Ray};
+use crate::prelude::{Hit, AABB, HitRecord, Ray, Vec3};
+use crate::utils::{pick, RngCore};
 
-pub struct HitList<T: Hit>(Vec<T>);
+#[derive(Clone)]
+pub struct HitList<T: Hit> {
+    list: Vec<T>,
+    emitters: usize,
+}
 
 impl<T: Hit> HitList<T> {
     pub fn new(list: Vec<T>) -> Self {
-        Self(list)
+        let emitters = list.iter().map(Hit::emitter_count).sum();
+        Self { list, emitters }
     }
 }
 
@@ -12,7 +18,7 @@ impl<T: Hit> Hit for HitList<T> {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
         let mut closest_so_far = t_max;
 
-        self.0.iter()
+        self.list.iter()
             .filter_map(|hit| {
                 let rec = hit.hit(ray, t_min, closest_so_far)?;
                 closest_so_far = rec.t;
@@ -22,7 +28,7 @@ impl<T: Hit> Hit for HitList<T> {
     }
 
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
-        let mut hits = self.0.iter();
+        let mut hits = self.list.iter();
 
         let bbox = hits.next()?.bounding_box(t0, t1)?;
 
@@ -31,4 +37,76 @@ impl<T: Hit> Hit for HitList<T> {
             Some(AABB::surrounding_box(bbox_so_far, bbox))
         })
     }
+
+    fn emitter_count(&self) -> usize {
+        self.emitters
+    }
+
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        if self.emitters == 0 {
+            return 0.
+        }
+
+        let summed_pdf = self.list.iter()
+            .filter(|hit| hit.emitter_count() > 0)
+            .map(|hit| hit.emitter_count() as f32 * hit.emitter_pdf(origin, direction, time))
+            .sum::<f32>();
+
+        summed_pdf / self.emitters as f32
+    }
+
+    fn sample_emitter(&self, origin: Vec3, time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        let mut picked = pick(&mut *rng, self.emitters);
+
+        for hit in &self.list {
+            let count = hit.emitter_count();
+            if picked < count {
+                return hit.sample_emitter(origin, time, rng)
+            }
+            picked -= count;
+        }
+
+        Vec3::new(1., 0., 0.)
+    }
+
+    fn sample_emitter_surface(&self, time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        let mut picked = pick(&mut *rng, self.emitters);
+
+        for hit in &self.list {
+            let count = hit.emitter_count();
+            if picked < count {
+                let (rec, pdf) = hit.sample_emitter_surface(time, rng)?;
+                return Some((rec, pdf * count as f32 / self.emitters as f32))
+            }
+            picked -= count;
+        }
+
+        None
+    }
+
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        if self.emitters == 0 {
+            return 0.
+        }
+
+        let summed_pdf = self.list.iter()
+            .filter(|hit| hit.emitter_count() > 0)
+            .map(|hit| hit.emitter_count() as f32 * hit.emitter_surface_pdf(ray, t))
+            .sum::<f32>();
+
+        summed_pdf / self.emitters as f32
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let mut transmittance = 1.;
+
+        for hit in &self.list {
+            transmittance *= hit.transmittance(ray, t_min, t_max);
+            if transmittance <= 0. {
+                return 0.
+            }
+        }
+
+        transmittance
+    }
 }
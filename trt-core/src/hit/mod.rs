@@ -1,6 +1,8 @@
 use crate::prelude::{Material, AABB, Ray, Vec3};
-use crate::material::Isotropic;
+use crate::density::Density;
+use crate::material::{Anisotropic, Isotropic, PhaseFunction};
 use crate::texture::Constant;
+use crate::utils::RngCore;
 
 use std::{sync::Arc, rc::Rc};
 
@@ -11,12 +13,50 @@ pub struct HitRecord<'mat> {
     pub p: Vec3,
     pub normal: Vec3,
     pub mat: &'mat dyn Material,
+    // Identifies the primitive that was hit, see `object_id`
+    pub object: usize,
+}
+
+// Address of a primitive, standing for it in object id buffers
+pub fn object_id<T: ?Sized>(object: &T) -> usize {
+    object as *const T as *const () as usize
 }
 
 pub trait Hit {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>>;
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB>;
 
+    // Number of light emitting primitives that `sample_emitter` can pick from
+    fn emitter_count(&self) -> usize {
+        0
+    }
+
+    // Solid angle density, as seen from `origin`, of `sample_emitter` returning `direction`
+    fn emitter_pdf(&self, _origin: Vec3, _direction: Vec3, _time: f32) -> f32 {
+        0.
+    }
+
+    // Direction from `origin` towards a random point on one of the emitters
+    fn sample_emitter(&self, _origin: Vec3, _time: f32, _rng: &mut dyn RngCore) -> Vec3 {
+        Vec3::new(1., 0., 0.)
+    }
+
+    // Uniform point on the surface of one of the emitters, along with its area density
+    fn sample_emitter_surface(&self, _time: f32, _rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        None
+    }
+
+    // Area density of `sample_emitter_surface` returning the point `ray` hits at `t`
+    fn emitter_surface_pdf(&self, _ray: &Ray, _t: f32) -> f32 {
+        0.
+    }
+
+    // Share of light making it along `ray` between `t_min` and `t_max`: none
+    // through opaque surfaces, and an unbiased estimate through media
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        if self.hit(ray, t_min, t_max).is_some() { 0. } else { 1. }
+    }
+
     fn combine<Other: Hit>(self, other: Other) -> Combine<Self, Other>
     where
         Self: Sized
@@ -66,6 +106,30 @@ pub trait Hit {
     {
         ConstantMedium::new_iso(self, density, Constant::new(color.into()))
     }
+
+    fn constant_medium_with_phase(self, density: f32, color: impl Into<Vec3>, phase: PhaseFunction)
+        -> ConstantMedium<Self, Anisotropic<Constant>>
+    where
+        Self: Sized
+    {
+        ConstantMedium::new(self, density, Anisotropic::new(Constant::new(color.into()), phase))
+    }
+
+    fn heterogeneous_medium<D: Density>(self, density: D, color: impl Into<Vec3>)
+        -> HeterogeneousMedium<Self, D, Isotropic<Constant>>
+    where
+        Self: Sized
+    {
+        HeterogeneousMedium::new_iso(self, density, Constant::new(color.into()))
+    }
+
+    fn heterogeneous_medium_with_phase<D: Density>(self, density: D, color: impl Into<Vec3>, phase: PhaseFunction)
+        -> HeterogeneousMedium<Self, D, Anisotropic<Constant>>
+    where
+        Self: Sized
+    {
+        HeterogeneousMedium::new(self, density, Anisotropic::new(Constant::new(color.into()), phase))
+    }
 }
 
 impl<T: Hit + ?Sized> Hit for Box<T> {
@@ -75,6 +139,24 @@ impl<T: Hit + ?Sized> Hit for Box<T> {
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
         self.as_ref().bounding_box(t0, t1)
     }
+    fn emitter_count(&self) -> usize {
+        self.as_ref().emitter_count()
+    }
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        self.as_ref().emitter_pdf(origin, direction, time)
+    }
+    fn sample_emitter(&self, origin: Vec3, time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        self.as_ref().sample_emitter(origin, time, rng)
+    }
+    fn sample_emitter_surface(&self, time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        self.as_ref().sample_emitter_surface(time, rng)
+    }
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        self.as_ref().emitter_surface_pdf(ray, t)
+    }
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        self.as_ref().transmittance(ray, t_min, t_max)
+    }
 }
 
 impl<T: Hit + ?Sized> Hit for Rc<T> {
@@ -84,6 +166,24 @@ impl<T: Hit + ?Sized> Hit for Rc<T> {
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
         self.as_ref().bounding_box(t0, t1)
     }
+    fn emitter_count(&self) -> usize {
+        self.as_ref().emitter_count()
+    }
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        self.as_ref().emitter_pdf(origin, direction, time)
+    }
+    fn sample_emitter(&self, origin: Vec3, time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        self.as_ref().sample_emitter(origin, time, rng)
+    }
+    fn sample_emitter_surface(&self, time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        self.as_ref().sample_emitter_surface(time, rng)
+    }
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        self.as_ref().emitter_surface_pdf(ray, t)
+    }
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        self.as_ref().transmittance(ray, t_min, t_max)
+    }
 }
 
 impl<T: Hit + ?Sized> Hit for Arc<T> {
@@ -93,6 +193,24 @@ impl<T: Hit + ?Sized> Hit for Arc<T> {
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
         self.as_ref().bounding_box(t0, t1)
     }
+    fn emitter_count(&self) -> usize {
+        self.as_ref().emitter_count()
+    }
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        self.as_ref().emitter_pdf(origin, direction, time)
+    }
+    fn sample_emitter(&self, origin: Vec3, time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        self.as_ref().sample_emitter(origin, time, rng)
+    }
+    fn sample_emitter_surface(&self, time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        self.as_ref().sample_emitter_surface(time, rng)
+    }
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        self.as_ref().emitter_surface_pdf(ray, t)
+    }
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        self.as_ref().transmittance(ray, t_min, t_max)
+    }
 }
 
 #[macro_export]
@@ -138,3 +256,6 @@ pub use rotate::{RotateY, RotateX, RotateZ};
 
 mod constant_medium;
 pub use constant_medium::ConstantMedium;
+
+mod heterogeneous_medium;
+pub use heterogeneous_medium::HeterogeneousMedium;
@@ -1,5 +1,9 @@
 use crate::prelude::{Material, Hit, AABB, HitRecord, Ray, Vec3, Asf32};
+use crate::hit::object_id;
+use crate::debug::count_primitive_test;
 use crate::material::MaterialBuilder;
+use crate::utils::{random_in_unit_sphere, sample_sphere_solid_angle, sphere_solid_angle_pdf, same_hit, RngCore};
+use std::f32::consts::PI;
 
 pub struct MovingSphere<T> {
     center0: Vec3,
@@ -26,6 +30,8 @@ impl MovingSphere<UnboundedMat> {
 
 impl<T: Material> Hit for MovingSphere<T> {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        count_primitive_test();
+
         let oc = ray.origin - self.center(ray.time);
         let a = Vec3::dot(ray.direction, ray.direction);
         let b = Vec3::dot(oc, ray.direction);
@@ -39,7 +45,7 @@ impl<T: Material> Hit for MovingSphere<T> {
                 if solution < t_max && solution > t_min {
                     let p = ray.point_at_parameter(solution);
                     let normal = (p - self.center(ray.time)) / self.radius;
-                    return Some(HitRecord { t: solution, p, normal, mat: &self.material, u: 0., v: 0. })
+                    return Some(HitRecord { t: solution, p, normal, mat: &self.material, u: 0., v: 0., object: object_id(self) })
                 }
             }
         }
@@ -61,6 +67,37 @@ impl<T: Material> Hit for MovingSphere<T> {
 
         Some(AABB::surrounding_box(box0, box1))
     }
+
+    fn emitter_count(&self) -> usize {
+        self.material.is_emissive() as usize
+    }
+
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        sphere_solid_angle_pdf(self.center(time), self.radius, origin, direction)
+    }
+
+    fn sample_emitter(&self, origin: Vec3, time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        sample_sphere_solid_angle(self.center(time), self.radius, origin, rng)
+    }
+
+    fn sample_emitter_surface(&self, time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        if !self.material.is_emissive() {
+            return None
+        }
+
+        let normal = random_in_unit_sphere(rng);
+        let p = self.center(time) + self.radius * normal;
+        let rec = HitRecord { t: 0., p, normal, mat: &self.material, u: 0., v: 0., object: object_id(self) };
+
+        Some((rec, 1. / (4. * PI * self.radius * self.radius)))
+    }
+
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        match self.hit(ray, 0.001, std::f32::MAX) {
+            Some(rec) if same_hit(rec.t, t) => 1. / (4. * PI * self.radius * self.radius),
+            _ => 0.,
+        }
+    }
 }
 
 #[derive(Default)]
@@ -1,5 +1,8 @@
 use crate::prelude::{Material, Hit, AABB, HitRecord, Ray, Vec3, Dimension, X, Y, Z, Asf32};
+use crate::hit::object_id;
+use crate::debug::count_primitive_test;
 use crate::material::MaterialBuilder;
+use crate::utils::{same_hit, Rng, RngCore};
 use std::{ops::RangeInclusive, marker::PhantomData};
 
 type DimRange = RangeInclusive<f32>;
@@ -20,6 +23,8 @@ where
     Mat: Material,
 {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        count_primitive_test();
+
         let t = (self.d3 - ray.origin.get::<D3>()) / ray.direction.get::<D3>();
 
         if t < t_min || t > t_max {
@@ -44,6 +49,7 @@ where
             mat: &self.material,
             p: ray.point_at_parameter(t),
             normal: Vec3::splat(0.).set::<D3>(1.),
+            object: object_id(self),
         })
     }
 
@@ -56,6 +62,74 @@ where
 
         Some(AABB { min, max })
     }
+
+    fn emitter_count(&self) -> usize {
+        self.material.is_emissive() as usize
+    }
+
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        let ray = Ray { origin, direction, time, wavelength: None, seed: 0 };
+
+        match self.hit(&ray, 0.001, std::f32::MAX) {
+            Some(rec) => {
+                let distance_squared = rec.t * rec.t * direction.squared_len();
+                let cosine = (direction.get::<D3>() / direction.len()).abs();
+
+                distance_squared / (cosine * self.area())
+            },
+            None => 0.,
+        }
+    }
+
+    fn sample_emitter(&self, origin: Vec3, _time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        let (point, _, _) = self.sample_point(rng);
+        point - origin
+    }
+
+    fn sample_emitter_surface(&self, _time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        if !self.material.is_emissive() {
+            return None
+        }
+
+        let (p, u, v) = self.sample_point(rng);
+        let normal = Vec3::splat(0.).set::<D3>(1.);
+        let rec = HitRecord { t: 0., p, normal, mat: &self.material, u, v, object: object_id(self) };
+
+        Some((rec, 1. / self.area()))
+    }
+
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        match self.hit(ray, 0.001, std::f32::MAX) {
+            Some(rec) if same_hit(rec.t, t) => 1. / self.area(),
+            _ => 0.,
+        }
+    }
+}
+
+impl<D1, D2, D3, Mat> Rect<D1, D2, D3, Mat>
+where
+    D1: Dimension,
+    D2: Dimension,
+    D3: Dimension,
+{
+    fn area(&self) -> f32 {
+        let (d1_0, d1_1) = (self.d1_range.start(), self.d1_range.end());
+        let (d2_0, d2_1) = (self.d2_range.start(), self.d2_range.end());
+
+        (d1_1 - d1_0) * (d2_1 - d2_0)
+    }
+
+    // Uniform point on the rectangle, along with its texture coordinates
+    fn sample_point(&self, rng: &mut dyn RngCore) -> (Vec3, f32, f32) {
+        let (d1_0, d1_1) = (self.d1_range.start(), self.d1_range.end());
+        let (d2_0, d2_1) = (self.d2_range.start(), self.d2_range.end());
+
+        let (u, v) = (rng.gen::<f32>(), rng.gen::<f32>());
+        let d1 = d1_0 + u * (d1_1 - d1_0);
+        let d2 = d2_0 + v * (d2_1 - d2_0);
+
+        (Vec3::splat(self.d3).set::<D1>(d1).set::<D2>(d2), u, v)
+    }
 }
 
 pub struct RectBuilder;
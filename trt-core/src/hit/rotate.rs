@@ -1,4 +1,4 @@
-use crate::{prelude::{Hit, AABB, HitRecord, Ray, Vec3, X, Y, Z}};
+use crate::{prelude::{Hit, AABB, HitRecord, Ray, Vec3, X, Y, Z}, utils::RngCore};
 
 pub struct RotateY<T: Hit> {
     hittable: T,
@@ -22,36 +22,32 @@ impl<T: Hit> RotateY<T> {
             bbox
         }
     }
+
+    fn to_object(&self, v: Vec3) -> Vec3 {
+        v.set::<X>(self.cos_theta * v.x() - self.sin_theta * v.z())
+            .set::<Z>(self.sin_theta * v.x() + self.cos_theta * v.z())
+    }
+
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        v.set::<X>(self.cos_theta * v.x() + self.sin_theta * v.z())
+            .set::<Z>(-self.sin_theta * v.x() + self.cos_theta * v.z())
+    }
 }
 
 impl<T: Hit> Hit for RotateY<T> {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
-        let origin = ray.origin
-            .set::<X>(self.cos_theta * ray.origin.x() - self.sin_theta * ray.origin.z())
-            .set::<Z>(self.sin_theta * ray.origin.x() + self.cos_theta * ray.origin.z());
-
-        let direction = ray.direction
-            .set::<X>(self.cos_theta * ray.direction.x() - self.sin_theta * ray.direction.z())
-            .set::<Z>(self.sin_theta * ray.direction.x() + self.cos_theta * ray.direction.z());
-
         let rotated_ray = Ray {
-            origin,
-            direction,
+            origin: self.to_object(ray.origin),
+            direction: self.to_object(ray.direction),
             time: ray.time,
+            wavelength: ray.wavelength,
+            seed: ray.seed,
         };
 
         let mut rec = self.hittable.hit(&rotated_ray, t_min, t_max)?;
 
-        let p = rec.p
-            .set::<X>(self.cos_theta * rec.p.x() + self.sin_theta * rec.p.z())
-            .set::<Z>(-self.sin_theta * rec.p.x() + self.cos_theta * rec.p.z());
-
-        let normal = rec.normal
-            .set::<X>(self.cos_theta * rec.normal.x() + self.sin_theta * rec.normal.z())
-            .set::<Z>(-self.sin_theta * rec.normal.x() + self.cos_theta * rec.normal.z());
-
-        rec.p = p;
-        rec.normal = normal;
+        rec.p = self.to_world(rec.p);
+        rec.normal = self.to_world(rec.normal);
 
         Some(rec)
     }
@@ -59,6 +55,48 @@ impl<T: Hit> Hit for RotateY<T> {
     fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
         self.bbox.clone()
     }
+
+    fn emitter_count(&self) -> usize {
+        self.hittable.emitter_count()
+    }
+
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        self.hittable.emitter_pdf(self.to_object(origin), self.to_object(direction), time)
+    }
+
+    fn sample_emitter(&self, origin: Vec3, time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        let direction = self.hittable.sample_emitter(self.to_object(origin), time, rng);
+        self.to_world(direction)
+    }
+
+    fn sample_emitter_surface(&self, time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        let (mut rec, pdf) = self.hittable.sample_emitter_surface(time, rng)?;
+        rec.p = self.to_world(rec.p);
+        rec.normal = self.to_world(rec.normal);
+        Some((rec, pdf))
+    }
+
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        let rotated_ray = Ray {
+            origin: self.to_object(ray.origin),
+            direction: self.to_object(ray.direction),
+            time: ray.time,
+            wavelength: ray.wavelength,
+            seed: ray.seed,
+        };
+        self.hittable.emitter_surface_pdf(&rotated_ray, t)
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let rotated_ray = Ray {
+            origin: self.to_object(ray.origin),
+            direction: self.to_object(ray.direction),
+            time: ray.time,
+            wavelength: ray.wavelength,
+            seed: ray.seed,
+        };
+        self.hittable.transmittance(&rotated_ray, t_min, t_max)
+    }
 }
 
 pub struct RotateX<T: Hit> {
@@ -83,36 +121,32 @@ impl<T: Hit> RotateX<T> {
             bbox
         }
     }
+
+    fn to_object(&self, v: Vec3) -> Vec3 {
+        v.set::<Y>(self.cos_theta * v.y() + self.sin_theta * v.z())
+            .set::<Z>(-self.sin_theta * v.y() + self.cos_theta * v.z())
+    }
+
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        v.set::<Y>(self.cos_theta * v.y() - self.sin_theta * v.z())
+            .set::<Z>(self.sin_theta * v.y() + self.cos_theta * v.z())
+    }
 }
 
 impl<T: Hit> Hit for RotateX<T> {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
-        let origin = ray.origin
-            .set::<Y>(self.cos_theta * ray.origin.y() + self.sin_theta * ray.origin.z())
-            .set::<Z>(-self.sin_theta * ray.origin.y() + self.cos_theta * ray.origin.z());
-
-        let direction = ray.direction
-            .set::<Y>(self.cos_theta * ray.direction.y() + self.sin_theta * ray.direction.z())
-            .set::<Z>(-self.sin_theta * ray.direction.y() + self.cos_theta * ray.direction.z());
-
         let rotated_ray = Ray {
-            origin,
-            direction,
+            origin: self.to_object(ray.origin),
+            direction: self.to_object(ray.direction),
             time: ray.time,
+            wavelength: ray.wavelength,
+            seed: ray.seed,
         };
 
         let mut rec = self.hittable.hit(&rotated_ray, t_min, t_max)?;
 
-        let p = rec.p
-            .set::<Y>(self.cos_theta * rec.p.y() - self.sin_theta * rec.p.z())
-            .set::<Z>(self.sin_theta * rec.p.y() + self.cos_theta * rec.p.z());
-
-        let normal = rec.normal
-            .set::<Y>(self.cos_theta * rec.normal.y() - self.sin_theta * rec.normal.z())
-            .set::<Z>(self.sin_theta * rec.normal.y() + self.cos_theta * rec.normal.z());
-
-        rec.p = p;
-        rec.normal = normal;
+        rec.p = self.to_world(rec.p);
+        rec.normal = self.to_world(rec.normal);
 
         Some(rec)
     }
@@ -120,6 +154,48 @@ impl<T: Hit> Hit for RotateX<T> {
     fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
         self.bbox.clone()
     }
+
+    fn emitter_count(&self) -> usize {
+        self.hittable.emitter_count()
+    }
+
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        self.hittable.emitter_pdf(self.to_object(origin), self.to_object(direction), time)
+    }
+
+    fn sample_emitter(&self, origin: Vec3, time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        let direction = self.hittable.sample_emitter(self.to_object(origin), time, rng);
+        self.to_world(direction)
+    }
+
+    fn sample_emitter_surface(&self, time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        let (mut rec, pdf) = self.hittable.sample_emitter_surface(time, rng)?;
+        rec.p = self.to_world(rec.p);
+        rec.normal = self.to_world(rec.normal);
+        Some((rec, pdf))
+    }
+
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        let rotated_ray = Ray {
+            origin: self.to_object(ray.origin),
+            direction: self.to_object(ray.direction),
+            time: ray.time,
+            wavelength: ray.wavelength,
+            seed: ray.seed,
+        };
+        self.hittable.emitter_surface_pdf(&rotated_ray, t)
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let rotated_ray = Ray {
+            origin: self.to_object(ray.origin),
+            direction: self.to_object(ray.direction),
+            time: ray.time,
+            wavelength: ray.wavelength,
+            seed: ray.seed,
+        };
+        self.hittable.transmittance(&rotated_ray, t_min, t_max)
+    }
 }
 
 pub struct RotateZ<T: Hit> {
@@ -144,36 +220,32 @@ impl<T: Hit> RotateZ<T> {
             bbox
         }
     }
+
+    fn to_object(&self, v: Vec3) -> Vec3 {
+        v.set::<X>(self.cos_theta * v.x() + self.sin_theta * v.y())
+            .set::<Y>(-self.sin_theta * v.x() + self.cos_theta * v.y())
+    }
+
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        v.set::<X>(self.cos_theta * v.x() - self.sin_theta * v.y())
+            .set::<Y>(self.sin_theta * v.x() + self.cos_theta * v.y())
+    }
 }
 
 impl<T: Hit> Hit for RotateZ<T> {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
-        let origin = ray.origin
-            .set::<X>(self.cos_theta * ray.origin.x() + self.sin_theta * ray.origin.y())
-            .set::<Y>(-self.sin_theta * ray.origin.x() + self.cos_theta * ray.origin.y());
-
-        let direction = ray.direction
-            .set::<X>(self.cos_theta * ray.direction.x() + self.sin_theta * ray.direction.y())
-            .set::<Y>(-self.sin_theta * ray.direction.x() + self.cos_theta * ray.direction.y());
-
         let rotated_ray = Ray {
-            origin,
-            direction,
+            origin: self.to_object(ray.origin),
+            direction: self.to_object(ray.direction),
             time: ray.time,
+            wavelength: ray.wavelength,
+            seed: ray.seed,
         };
 
         let mut rec = self.hittable.hit(&rotated_ray, t_min, t_max)?;
 
-        let p = rec.p
-            .set::<X>(self.cos_theta * rec.p.x() - self.sin_theta * rec.p.y())
-            .set::<Y>(self.sin_theta * rec.p.x() + self.cos_theta * rec.p.y());
-
-        let normal = rec.normal
-            .set::<X>(self.cos_theta * rec.normal.x() - self.sin_theta * rec.normal.y())
-            .set::<Y>(self.sin_theta * rec.normal.x() + self.cos_theta * rec.normal.y());
-
-        rec.p = p;
-        rec.normal = normal;
+        rec.p = self.to_world(rec.p);
+        rec.normal = self.to_world(rec.normal);
 
         Some(rec)
     }
@@ -181,6 +253,48 @@ impl<T: Hit> Hit for RotateZ<T> {
     fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
         self.bbox.clone()
     }
+
+    fn emitter_count(&self) -> usize {
+        self.hittable.emitter_count()
+    }
+
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        self.hittable.emitter_pdf(self.to_object(origin), self.to_object(direction), time)
+    }
+
+    fn sample_emitter(&self, origin: Vec3, time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        let direction = self.hittable.sample_emitter(self.to_object(origin), time, rng);
+        self.to_world(direction)
+    }
+
+    fn sample_emitter_surface(&self, time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        let (mut rec, pdf) = self.hittable.sample_emitter_surface(time, rng)?;
+        rec.p = self.to_world(rec.p);
+        rec.normal = self.to_world(rec.normal);
+        Some((rec, pdf))
+    }
+
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        let rotated_ray = Ray {
+            origin: self.to_object(ray.origin),
+            direction: self.to_object(ray.direction),
+            time: ray.time,
+            wavelength: ray.wavelength,
+            seed: ray.seed,
+        };
+        self.hittable.emitter_surface_pdf(&rotated_ray, t)
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let rotated_ray = Ray {
+            origin: self.to_object(ray.origin),
+            direction: self.to_object(ray.direction),
+            time: ray.time,
+            wavelength: ray.wavelength,
+            seed: ray.seed,
+        };
+        self.hittable.transmittance(&rotated_ray, t_min, t_max)
+    }
 }
 
 fn compute_bbox(bbox: AABB, cos_theta: f32, sin_theta: f32) -> AABB {
@@ -1,6 +1,9 @@
 use crate::prelude::{Material, Hit, AABB, HitRecord, Ray, Vec3, Asf32};
+use crate::hit::object_id;
+use crate::debug::count_primitive_test;
 use crate::material::MaterialBuilder;
-use crate::utils::sphere_uv;
+use crate::utils::{sphere_uv, random_in_unit_sphere, sample_sphere_solid_angle, sphere_solid_angle_pdf, same_hit, RngCore};
+use std::f32::consts::PI;
 
 pub struct Sphere<Mat> {
     center: Vec3,
@@ -18,6 +21,8 @@ impl Sphere<UnboundedMat> {
 
 impl<Mat: Material> Hit for Sphere<Mat> {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        count_primitive_test();
+
         let oc = ray.origin - self.center;
         let a = Vec3::dot(ray.direction, ray.direction);
         let b = Vec3::dot(oc, ray.direction);
@@ -31,7 +36,7 @@ impl<Mat: Material> Hit for Sphere<Mat> {
                     let p = ray.point_at_parameter(solution);
                     let normal = (p - self.center) / self.radius;
                     let (u, v) = sphere_uv((p - self.center) / self.radius);
-                    return Some(HitRecord { t: solution, p, normal, mat: &self.material, u, v })
+                    return Some(HitRecord { t: solution, p, normal, mat: &self.material, u, v, object: object_id(self) })
                 }
             }
         }
@@ -46,6 +51,38 @@ impl<Mat: Material> Hit for Sphere<Mat> {
             max: self.center + Vec3::splat(radius),
         })
     }
+
+    fn emitter_count(&self) -> usize {
+        self.material.is_emissive() as usize
+    }
+
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, _time: f32) -> f32 {
+        sphere_solid_angle_pdf(self.center, self.radius, origin, direction)
+    }
+
+    fn sample_emitter(&self, origin: Vec3, _time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        sample_sphere_solid_angle(self.center, self.radius, origin, rng)
+    }
+
+    fn sample_emitter_surface(&self, _time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        if !self.material.is_emissive() {
+            return None
+        }
+
+        let normal = random_in_unit_sphere(rng);
+        let (u, v) = sphere_uv(normal);
+        let p = self.center + self.radius * normal;
+        let rec = HitRecord { t: 0., p, normal, mat: &self.material, u, v, object: object_id(self) };
+
+        Some((rec, 1. / (4. * PI * self.radius * self.radius)))
+    }
+
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        match self.hit(ray, 0.001, std::f32::MAX) {
+            Some(rec) if same_hit(rec.t, t) => 1. / (4. * PI * self.radius * self.radius),
+            _ => 0.,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
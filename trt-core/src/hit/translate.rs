@@ -1,4 +1,5 @@
 use crate::prelude::{Hit, AABB, HitRecord, Ray, Vec3};
+use crate::utils::RngCore;
 
 pub struct Translate<T: Hit> {
     wrapped: T,
@@ -17,6 +18,8 @@ impl<T: Hit> Hit for Translate<T> {
             origin: ray.origin - self.offset,
             direction: ray.direction,
             time: ray.time,
+            wavelength: ray.wavelength,
+            seed: ray.seed,
         };
         let mut rec = self.wrapped.hit(&moved_ray, t_min, t_max)?;
         rec.p += self.offset;
@@ -30,4 +33,44 @@ impl<T: Hit> Hit for Translate<T> {
             max: bbox.max + self.offset,
         })
     }
+
+    fn emitter_count(&self) -> usize {
+        self.wrapped.emitter_count()
+    }
+
+    fn emitter_pdf(&self, origin: Vec3, direction: Vec3, time: f32) -> f32 {
+        self.wrapped.emitter_pdf(origin - self.offset, direction, time)
+    }
+
+    fn sample_emitter(&self, origin: Vec3, time: f32, rng: &mut dyn RngCore) -> Vec3 {
+        self.wrapped.sample_emitter(origin - self.offset, time, rng)
+    }
+
+    fn sample_emitter_surface(&self, time: f32, rng: &mut dyn RngCore) -> Option<(HitRecord<'_>, f32)> {
+        let (mut rec, pdf) = self.wrapped.sample_emitter_surface(time, rng)?;
+        rec.p += self.offset;
+        Some((rec, pdf))
+    }
+
+    fn emitter_surface_pdf(&self, ray: &Ray, t: f32) -> f32 {
+        let moved_ray = Ray {
+            origin: ray.origin - self.offset,
+            direction: ray.direction,
+            time: ray.time,
+            wavelength: ray.wavelength,
+            seed: ray.seed,
+        };
+        self.wrapped.emitter_surface_pdf(&moved_ray, t)
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let moved_ray = Ray {
+            origin: ray.origin - self.offset,
+            direction: ray.direction,
+            time: ray.time,
+            wavelength: ray.wavelength,
+            seed: ray.seed,
+        };
+        self.wrapped.transmittance(&moved_ray, t_min, t_max)
+    }
 }
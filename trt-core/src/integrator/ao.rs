@@ -0,0 +1,30 @@
+use crate::prelude::{Hit, Ray, Vec3};
+use crate::scene::Scene;
+use crate::utils::{random_in_unit_sphere, Rng};
+
+// Whether a cosine distributed ray from the first hit escapes further than
+// `radius`, averaging to the share of the hemisphere left open. Rays
+// missing the world are white
+pub fn radiance(ray: Ray, scene: &Scene<impl Hit>, radius: f32, rng: impl Rng) -> Vec3 {
+    let world = &scene.world;
+
+    let rec = match world.hit(&ray, 0.001, std::f32::MAX) {
+        Some(rec) => rec,
+        None => return Vec3::splat(1.),
+    };
+
+    // Facing the camera, back faces included
+    let normal = if Vec3::dot(rec.normal, ray.direction) > 0. { -rec.normal } else { rec.normal };
+    let occlusion_ray = Ray {
+        origin: rec.p,
+        direction: (normal + random_in_unit_sphere(rng)).unit(),
+        time: ray.time,
+        wavelength: ray.wavelength,
+        seed: ray.seed,
+    };
+
+    match world.hit(&occlusion_ray, 0.001, radius) {
+        Some(_) => Vec3::splat(0.),
+        None => Vec3::splat(1.),
+    }
+}
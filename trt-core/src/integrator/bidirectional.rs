@@ -0,0 +1,362 @@
+use crate::prelude::{Hit, HitRecord, Ray, Vec3};
+use crate::scene::Scene;
+use crate::spectrum::at_wavelength;
+use crate::utils::{random_in_unit_sphere, Rng};
+
+use std::f32::consts::PI;
+
+// A point along the camera or the light subpath, densities are per unit area
+struct Vertex<'a> {
+    p: Vec3,
+    // `None` for the camera
+    rec: Option<HitRecord<'a>>,
+    // Direction the subpath arrived along
+    incoming: Vec3,
+    throughput: Vec3,
+    pdf_fwd: f32,
+    pdf_rev: f32,
+    specular: bool,
+    wavelength: Option<f32>,
+}
+
+impl<'a> Vertex<'a> {
+    fn camera(p: Vec3, wavelength: Option<f32>) -> Self {
+        Self {
+            p,
+            rec: None,
+            incoming: Vec3::splat(0.),
+            throughput: Vec3::splat(1.),
+            pdf_fwd: 0.,
+            pdf_rev: 0.,
+            specular: false,
+            wavelength,
+        }
+    }
+
+    // Media scatter the same way in every direction, without foreshortening
+    fn cosine(&self, direction: Vec3) -> f32 {
+        match &self.rec {
+            Some(rec) if !rec.mat.is_volumetric() => Vec3::dot(rec.normal, direction).abs() / direction.len(),
+            _ => 1.,
+        }
+    }
+
+    // Converts the solid angle density of going from this vertex to `next` into an area density
+    fn area_pdf(&self, pdf: f32, next: &Vertex) -> f32 {
+        let to_next = next.p - self.p;
+        pdf * next.cosine(to_next) / to_next.squared_len()
+    }
+
+    // BSDF times cosine towards `to`
+    fn eval(&self, to: Vec3) -> Vec3 {
+        match &self.rec {
+            Some(rec) => at_wavelength(rec.mat.eval(rec, self.incoming, to - self.p), self.wavelength),
+            None => Vec3::splat(0.),
+        }
+    }
+
+    // Solid angle density of scattering towards `to` when arriving from `from`
+    fn pdf(&self, from: Vec3, to: Vec3) -> f32 {
+        match &self.rec {
+            Some(rec) => rec.mat.scattering_pdf(rec, self.p - from, to - self.p),
+            None => 0.,
+        }
+    }
+
+    fn emitted(&self) -> Vec3 {
+        match &self.rec {
+            Some(rec) if rec.mat.is_emissive() => at_wavelength(rec.mat.emitted(rec.u, rec.v, rec.p), self.wavelength),
+            _ => Vec3::splat(0.),
+        }
+    }
+
+    // Emitters are two-sided, with a cosine distribution on each side
+    fn emission_pdf(&self, to: Vec3) -> f32 {
+        self.cosine(to - self.p) / (2. * PI)
+    }
+}
+
+pub fn radiance(ray: Ray, scene: &Scene<impl Hit>, mut rng: impl Rng) -> Vec3 {
+    let world = &scene.world;
+    let max_depth = scene.max_depth();
+    let (time, wavelength) = (ray.time, ray.wavelength);
+
+    let mut camera_path = vec![Vertex::camera(ray.origin, wavelength)];
+    let escaped = random_walk(world, ray, Vec3::splat(1.), 0., max_depth + 2, &mut camera_path, &mut rng);
+
+    let mut light_path = Vec::with_capacity(max_depth + 1);
+    if world.emitter_count() > 0 {
+        light_subpath(world, time, wavelength, max_depth + 1, &mut light_path, &mut rng);
+    }
+
+    // There is no way to sample the background from the light side
+    let mut color = escaped.map_or(Vec3::splat(0.), |(throughput, direction)| {
+        throughput * at_wavelength(scene.background(direction), wavelength)
+    });
+
+    // Camera subpaths of a single vertex would have to be splatted onto other pixels
+    for t in 2..=camera_path.len() {
+        for s in 0..=light_path.len().max(1) {
+            if s + t - 2 > max_depth {
+                break
+            }
+            color += connect(world, &light_path, &camera_path, s, t, time, &mut rng);
+        }
+    }
+
+    color
+}
+
+fn light_subpath<'a>(
+    world: &'a impl Hit,
+    time: f32,
+    wavelength: Option<f32>,
+    max_vertices: usize,
+    path: &mut Vec<Vertex<'a>>,
+    rng: &mut impl Rng,
+) {
+    let (rec, pdf_pos) = match world.sample_emitter_surface(time, rng) {
+        Some((rec, pdf)) if pdf > 0. => (rec, pdf),
+        _ => return,
+    };
+
+    let normal = if rng.gen::<bool>() { rec.normal } else { -rec.normal };
+    let direction = normal + random_in_unit_sphere(&mut *rng);
+
+    let light = Vertex {
+        p: rec.p,
+        incoming: Vec3::splat(0.),
+        throughput: at_wavelength(rec.mat.emitted(rec.u, rec.v, rec.p), wavelength) / pdf_pos,
+        pdf_fwd: pdf_pos,
+        pdf_rev: 0.,
+        specular: false,
+        wavelength,
+        rec: Some(rec),
+    };
+
+    let pdf_dir = light.emission_pdf(light.p + direction);
+    if pdf_dir <= 0. {
+        return
+    }
+
+    let throughput = light.throughput * light.cosine(direction) / pdf_dir;
+    let ray = Ray { origin: light.p, direction, time, wavelength, seed: rng.gen() };
+
+    path.push(light);
+    random_walk(world, ray, throughput, pdf_dir, max_vertices, path, rng);
+}
+
+// Extends `path` by scattering `ray` until it escapes, gets absorbed, or
+// reaches `max_vertices`, returns the throughput and direction of an escaped ray
+fn random_walk<'a>(
+    world: &'a impl Hit,
+    mut ray: Ray,
+    mut throughput: Vec3,
+    mut pdf: f32,
+    max_vertices: usize,
+    path: &mut Vec<Vertex<'a>>,
+    rng: &mut impl Rng,
+) -> Option<(Vec3, Vec3)> {
+    while path.len() < max_vertices {
+        let rec = match world.hit(&ray, 0.001, std::f32::MAX) {
+            Some(rec) => rec,
+            None => return Some((throughput, ray.direction)),
+        };
+
+        let specular = rec.mat.is_specular();
+        let scatter = rec.mat.scatter(&ray, &rec, rng);
+        let pdf_rev = match &scatter {
+            Some(scatter) if !specular => rec.mat.scattering_pdf(&rec, -scatter.ray.direction, -ray.direction),
+            _ => 0.,
+        };
+
+        let prev = path.last().expect("random walk without a starting vertex");
+        let mut vertex = Vertex {
+            p: rec.p,
+            incoming: ray.direction,
+            throughput,
+            pdf_fwd: 0.,
+            pdf_rev: 0.,
+            specular,
+            wavelength: ray.wavelength,
+            rec: Some(rec),
+        };
+        vertex.pdf_fwd = prev.area_pdf(pdf, &vertex);
+
+        let prev_pdf_rev = vertex.area_pdf(pdf_rev, prev);
+        if let Some(prev) = path.last_mut() {
+            prev.pdf_rev = prev_pdf_rev;
+        }
+        path.push(vertex);
+
+        let scatter = scatter?;
+        pdf = if specular { 0. } else { scatter.pdf };
+        throughput *= at_wavelength(scatter.attenuation, ray.wavelength);
+        ray = scatter.ray;
+    }
+
+    None
+}
+
+// Contribution of the path made of the first `s` light and `t` camera vertices
+fn connect<'a>(
+    world: &'a impl Hit,
+    light_path: &[Vertex<'a>],
+    camera_path: &[Vertex<'a>],
+    s: usize,
+    t: usize,
+    time: f32,
+    rng: &mut impl Rng,
+) -> Vec3 {
+    let pt = &camera_path[t - 1];
+
+    let (contribution, sampled) = match s {
+        0 => (pt.throughput * pt.emitted(), None),
+        1 => {
+            if pt.specular {
+                return Vec3::splat(0.)
+            }
+
+            // Sampled by area like the first vertex of light subpaths, so that
+            // both strategies agree on its density
+            let (light, pdf_pos) = match world.sample_emitter_surface(time, rng) {
+                Some((light, pdf)) if pdf > 0. => (light, pdf),
+                _ => return Vec3::splat(0.),
+            };
+
+            // Leaves `pt` by the usual offset whatever the distance, while the
+            // far end allows for the precision of hits on distant lights
+            let to_light = light.p - pt.p;
+            let shadow_ray = Ray { origin: pt.p, direction: to_light.unit(), time, wavelength: None, seed: rng.gen() };
+            if world.hit(&shadow_ray, 0.001, to_light.len() * (1. - 0.001)).is_some() {
+                return Vec3::splat(0.)
+            }
+
+            let emitted = at_wavelength(light.mat.emitted(light.u, light.v, light.p), pt.wavelength);
+            let vertex = Vertex {
+                p: light.p,
+                incoming: Vec3::splat(0.),
+                throughput: emitted / pdf_pos,
+                pdf_fwd: pdf_pos,
+                pdf_rev: 0.,
+                specular: false,
+                wavelength: pt.wavelength,
+                rec: Some(light),
+            };
+
+            let geometry = vertex.cosine(to_light) / to_light.squared_len();
+            (pt.throughput * pt.eval(vertex.p) * vertex.throughput * geometry, Some(vertex))
+        },
+        _ => {
+            let qs = &light_path[s - 1];
+            if pt.specular || qs.specular {
+                return Vec3::splat(0.)
+            }
+
+            let bsdf = qs.eval(pt.p) * pt.eval(qs.p);
+            if bsdf.max_element(0.) <= 0. {
+                return Vec3::splat(0.)
+            }
+
+            let shadow_ray = Ray { origin: pt.p, direction: qs.p - pt.p, time, wavelength: None, seed: rng.gen() };
+            if world.hit(&shadow_ray, 0.001, 1. - 0.001).is_some() {
+                return Vec3::splat(0.)
+            }
+
+            (qs.throughput * bsdf * pt.throughput / (qs.p - pt.p).squared_len(), None)
+        },
+    };
+
+    if contribution.max_element(0.) <= 0. {
+        return Vec3::splat(0.)
+    }
+
+    contribution * mis_weight(world, light_path, camera_path, sampled.as_ref(), s, t, time)
+}
+
+// Power heuristic over every strategy which could have produced the same
+// path, computed from the ratios of their densities to the current one
+fn mis_weight(
+    world: &impl Hit,
+    light_path: &[Vertex],
+    camera_path: &[Vertex],
+    sampled: Option<&Vertex>,
+    s: usize,
+    t: usize,
+    time: f32,
+) -> f32 {
+    let pt = &camera_path[t - 1];
+    let pt_minus = &camera_path[t - 2];
+    let qs = if s > 0 { sampled.or_else(|| light_path.get(s - 1)) } else { None };
+    let qs_minus = if s > 1 { light_path.get(s - 2) } else { None };
+
+    // The reverse densities around the connection depend on the strategy
+    let pt_pdf_rev = match (qs, qs_minus) {
+        (None, _) => match &pt.rec {
+            Some(rec) => {
+                let ray = Ray { origin: pt_minus.p, direction: pt.incoming, time, wavelength: None, seed: 0 };
+                world.emitter_surface_pdf(&ray, rec.t)
+            },
+            None => 0.,
+        },
+        (Some(qs), None) => qs.area_pdf(qs.emission_pdf(pt.p), pt),
+        (Some(qs), Some(qs_minus)) => qs.area_pdf(qs.pdf(qs_minus.p, pt.p), pt),
+    };
+    let pt_minus_pdf_rev = match qs {
+        None => pt.area_pdf(pt.emission_pdf(pt_minus.p), pt_minus),
+        Some(qs) => pt.area_pdf(pt.pdf(qs.p, pt_minus.p), pt_minus),
+    };
+    let qs_pdf_rev = qs.map_or(0., |qs| pt.area_pdf(pt.pdf(pt_minus.p, qs.p), qs));
+    let qs_minus_pdf_rev = match (qs, qs_minus) {
+        (Some(qs), Some(qs_minus)) => qs.area_pdf(qs.pdf(pt.p, qs_minus.p), qs_minus),
+        _ => 0.,
+    };
+
+    let remap = |pdf: f32| if pdf != 0. { pdf * pdf } else { 1. };
+    let mut summed_ratios = 0.;
+
+    let mut ratio = 1.;
+    for i in (2..t).rev() {
+        let pdf_rev = match i {
+            _ if i == t - 1 => pt_pdf_rev,
+            _ if i == t - 2 => pt_minus_pdf_rev,
+            _ => camera_path[i].pdf_rev,
+        };
+        ratio *= remap(pdf_rev) / remap(camera_path[i].pdf_fwd);
+
+        if !camera_path[i].specular && !camera_path[i - 1].specular {
+            summed_ratios += ratio;
+        }
+    }
+
+    let mut ratio = 1.;
+    for i in (0..s).rev() {
+        let (vertex, pdf_rev) = match i {
+            _ if i == s - 1 => (qs.expect("connection without a light vertex"), qs_pdf_rev),
+            _ if i == s - 2 => (&light_path[i], qs_minus_pdf_rev),
+            _ => (&light_path[i], light_path[i].pdf_rev),
+        };
+        ratio *= remap(pdf_rev) / remap(vertex.pdf_fwd);
+
+        if !vertex.specular && !(i > 0 && light_path[i - 1].specular) {
+            summed_ratios += ratio;
+        }
+    }
+
+    1. / (1. + summed_ratios)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::integrator::Integrator;
+    use crate::integrator::tests::{assert_close, cornell_box, mean_radiance};
+
+    #[test]
+    fn converges_to_path_tracing() {
+        let mut scene = cornell_box(256);
+        let reference = mean_radiance(&scene);
+
+        scene.integrator = Integrator::Bidirectional;
+        assert_close(mean_radiance(&scene), reference, 0.05, "bidirectional");
+    }
+}
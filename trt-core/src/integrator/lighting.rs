@@ -0,0 +1,72 @@
+use crate::prelude::{Hit, HitRecord, Ray, Vec3};
+use crate::environment::Environment;
+use crate::spectrum::at_wavelength;
+use crate::utils::{power_heuristic, Rng};
+use super::surface_behind_media;
+
+// Light reaching `rec` from a sampled emitter. With `mis`, weighted against
+// the bounces that may find it too, for integrators also counting the
+// emitters their paths run into
+pub fn direct_lighting(ray: &Ray, rec: &HitRecord, world: &impl Hit, mis: bool, rng: &mut impl Rng) -> Vec3 {
+    let direction = world.sample_emitter(rec.p, ray.time, rng);
+
+    let emitter_pdf = world.emitter_pdf(rec.p, direction, ray.time);
+    if emitter_pdf <= 0. {
+        return Vec3::splat(0.)
+    }
+
+    let shadow_ray = Ray {
+        origin: rec.p,
+        direction,
+        time: ray.time,
+        wavelength: ray.wavelength,
+        seed: ray.seed,
+    };
+
+    let light = match surface_behind_media(world, &shadow_ray) {
+        Some(light) if light.mat.is_emissive() => light,
+        _ => return Vec3::splat(0.),
+    };
+
+    // Dimmed rather than cut off by media in the way
+    let transmittance = world.transmittance(&shadow_ray, 0.001, light.t * (1. - 0.001));
+    let emitted = light.mat.emitted(light.u, light.v, light.p) * transmittance;
+
+    shade(ray, rec, direction, emitted, emitter_pdf, mis)
+}
+
+// Same for a direction sampled from the environment
+pub fn environment_lighting(ray: &Ray, rec: &HitRecord, world: &impl Hit, environment: &dyn Environment, mis: bool, rng: &mut impl Rng) -> Vec3 {
+    let (direction, environment_pdf) = match environment.sample(rng) {
+        Some((direction, pdf)) if pdf > 0. => (direction, pdf),
+        _ => return Vec3::splat(0.),
+    };
+
+    let shadow_ray = Ray {
+        origin: rec.p,
+        direction,
+        time: ray.time,
+        wavelength: ray.wavelength,
+        seed: ray.seed,
+    };
+
+    let transmittance = world.transmittance(&shadow_ray, 0.001, std::f32::MAX);
+    if transmittance <= 0. {
+        return Vec3::splat(0.)
+    }
+
+    shade(ray, rec, direction, environment.radiance(direction) * transmittance, environment_pdf, mis)
+}
+
+// Estimate of `radiance` arriving from `direction`, sampled with `light_pdf`,
+// and scattered back along `ray`
+fn shade(ray: &Ray, rec: &HitRecord, direction: Vec3, radiance: Vec3, light_pdf: f32, mis: bool) -> Vec3 {
+    let bsdf = rec.mat.eval(rec, ray.direction, direction);
+    let weight = if mis {
+        power_heuristic(light_pdf, rec.mat.scattering_pdf(rec, ray.direction, direction))
+    } else {
+        1.
+    };
+
+    at_wavelength(bsdf, ray.wavelength) * at_wavelength(radiance, ray.wavelength) * weight / light_pdf
+}
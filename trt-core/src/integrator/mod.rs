@@ -0,0 +1,125 @@
+mod ao;
+mod bidirectional;
+mod lighting;
+mod path;
+mod photon;
+mod whitted;
+
+pub use photon::PhotonMap;
+
+use crate::prelude::{Hit, HitRecord, Ray, Vec3};
+use crate::scene::Scene;
+use crate::utils::Rng;
+
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub enum Integrator {
+    PathTracing,
+    // Connects camera and light subpaths, russian roulette isn't used there
+    Bidirectional,
+    // Path tracing with caustics gathered from a photon map of the same world
+    PhotonMapping(Arc<PhotonMap>),
+    // Cheap previews: occlusion of the first hits within `radius`, and
+    // direct lighting through perfect reflections and refractions
+    AmbientOcclusion { radius: f32 },
+    Whitted,
+}
+
+impl Default for Integrator {
+    fn default() -> Self {
+        Integrator::PathTracing
+    }
+}
+
+impl Integrator {
+    pub fn photon_mapping(world: &impl Hit, photons: usize, radius: f32, max_depth: usize, rng: impl Rng) -> Self {
+        Integrator::PhotonMapping(Arc::new(PhotonMap::new(world, photons, radius, max_depth, rng)))
+    }
+
+    pub fn radiance(&self, ray: Ray, scene: &Scene<impl Hit>, rng: impl Rng) -> Vec3 {
+        match self {
+            Integrator::PathTracing => path::radiance(ray, scene, rng),
+            Integrator::Bidirectional => bidirectional::radiance(ray, scene, rng),
+            Integrator::PhotonMapping(photons) => path::trace(ray, scene, Some(photons), rng),
+            Integrator::AmbientOcclusion { radius } => ao::radiance(ray, scene, *radius, rng),
+            Integrator::Whitted => whitted::radiance(ray, scene, rng),
+        }
+    }
+}
+
+// First surface along `ray`, looking through the media in the way rather
+// than stopping at the collisions sampled in them
+fn surface_behind_media<'a>(world: &'a impl Hit, ray: &Ray) -> Option<HitRecord<'a>> {
+    let mut t_min = 0.001;
+
+    loop {
+        let rec = world.hit(ray, t_min, std::f32::MAX)?;
+        if !rec.mat.is_volumetric() {
+            return Some(rec)
+        }
+
+        // However close the collision, so that the walk always moves on
+        t_min = rec.t.max(t_min) * (1. + 1e-6);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::camera::CameraBuilder;
+    use crate::hit::{RectBuilder, Sphere};
+    use crate::material::{Lambertian, MaterialBuilderExt, Metal};
+    use crate::prelude::{Hit, MaterialBuilder, Vec3};
+    use crate::scene::Scene;
+    use crate::world;
+
+    // A 9x7 view of `world` around the origin, lit by its emitters only
+    pub fn scene_of<W: Hit>(world: W, samples_per_px: u32) -> Scene<W> {
+        let (width, height) = (9, 7);
+        let camera = CameraBuilder::default()
+            .look_from((0., 1., -4.))
+            .look_at((0., 0.5, 0.))
+            .dimensions(width as f32, height as f32)
+            .finish();
+
+        Scene { seed: 7, ..Scene::new(camera, width, height, world, samples_per_px, 8) }
+    }
+
+    // Lit by a ceiling rect, with a mirror sphere for caustics
+    pub fn cornell_box(samples_per_px: u32) -> Scene<impl Hit> {
+        let (width, height) = (8, 8);
+        let white = || Lambertian::colored((0.73, 0.73, 0.73));
+
+        let camera = CameraBuilder::default()
+            .look_from((278., 278., -800.))
+            .look_at((278., 278., 0.))
+            .dimensions(width as f32, height as f32)
+            .finish();
+        let world = world![
+            RectBuilder.y(0..=555).z(0..=555).x(555).material(Lambertian::colored((0.12, 0.45, 0.15))).flip_normals(),
+            RectBuilder.y(0..=555).z(0..=555).x(0).material(Lambertian::colored((0.65, 0.05, 0.05))),
+            RectBuilder.x(0..=555).z(0..=555).y(555).material(white()).flip_normals(),
+            RectBuilder.x(0..=555).z(0..=555).y(0).material(white()),
+            RectBuilder.x(0..=555).y(0..=555).z(555).material(white()).flip_normals(),
+            RectBuilder.x(213..=343).z(227..=332).y(554).diffuse_color((15., 15., 15.)),
+            Sphere::builder().center((190, 90, 190)).radius(90).material(white()),
+            Sphere::builder().center((370, 120, 300)).radius(120).material(Metal::new(Vec3::splat(0.9), 0.)),
+        ];
+
+        Scene { seed: 7, ..Scene::new(camera, width, height, world, samples_per_px, 8) }
+    }
+
+    // Of the linear radiance over the whole image
+    pub fn mean_radiance(scene: &Scene<impl Hit>) -> Vec3 {
+        let framebuffer = scene.render();
+        let pixels = framebuffer.pixels();
+
+        pixels.iter().fold(Vec3::splat(0.), |sum, &pixel| sum + pixel) / pixels.len() as f32
+    }
+
+    pub fn assert_close(value: Vec3, reference: Vec3, tolerance: f32, name: &str) {
+        let difference = value - reference;
+        let error = (difference * difference).sqrt().max_element(0.) / reference.max_element(0.);
+        assert!(error < tolerance, "{}: {:?} against {:?}", name, value, reference);
+    }
+}
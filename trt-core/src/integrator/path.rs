@@ -0,0 +1,161 @@
+use crate::prelude::{Hit, Ray, Vec3};
+use crate::scene::Scene;
+use crate::spectrum::at_wavelength;
+use crate::utils::{power_heuristic, Rng};
+use super::lighting::{direct_lighting, environment_lighting};
+use super::photon::PhotonMap;
+
+pub fn radiance(ray: Ray, scene: &Scene<impl Hit>, rng: impl Rng) -> Vec3 {
+    trace(ray, scene, None, rng)
+}
+
+// With `caustics`, light reaching diffuse surfaces through specular bounces
+// comes from the photon map instead of the paths that would find it by chance
+pub fn trace(mut ray: Ray, scene: &Scene<impl Hit>, caustics: Option<&PhotonMap>, mut rng: impl Rng) -> Vec3 {
+    let world = &scene.world;
+    let roulette_depth = scene.roulette_depth();
+    let sample_emitters = world.emitter_count() > 0;
+
+    let mut color = PathRadiance::default();
+    let mut throughput = Vec3::splat(1.);
+    // Density of the bounce which produced `ray`, `None` for camera rays and
+    // specular bounces since emitters can't be sampled explicitly from there
+    let mut scatter_pdf = None;
+    // Whether the path went through a diffuse surface, with only specular bounces since
+    let mut after_diffuse = false;
+
+    for depth in 0..scene.max_depth() {
+        let rec = match world.hit(&ray, 0.001, std::f32::MAX) {
+            Some(rec) => rec,
+            None => {
+                color.add(depth, throughput * background(&ray, scene, scatter_pdf));
+                return color.total(scene.clamp_indirect)
+            },
+        };
+
+        let caustic = after_diffuse && scatter_pdf.is_none();
+        if rec.mat.is_emissive() && !(caustic && caustics.is_some()) {
+            let weight = match scatter_pdf {
+                Some(pdf) if sample_emitters => {
+                    let emitter_pdf = world.emitter_pdf(ray.origin, ray.direction, ray.time);
+                    power_heuristic(pdf, emitter_pdf)
+                },
+                _ => 1.,
+            };
+            color.add(depth, throughput * at_wavelength(rec.mat.emitted(rec.u, rec.v, rec.p), ray.wavelength) * weight);
+        }
+
+        let specular = rec.mat.is_specular();
+        if sample_emitters && !specular {
+            color.add(depth + 1, throughput * direct_lighting(&ray, &rec, world, true, &mut rng));
+        }
+
+        if let (Some(environment), false) = (&scene.environment, specular) {
+            color.add(depth + 1, throughput * environment_lighting(&ray, &rec, world, &**environment, true, &mut rng));
+        }
+
+        if let Some(photons) = caustics {
+            if !specular && !rec.mat.is_volumetric() {
+                color.add(depth + 1, throughput * at_wavelength(photons.radiance(&ray, &rec), ray.wavelength));
+            }
+        }
+
+        let scatter = match rec.mat.scatter(&ray, &rec, &mut rng) {
+            Some(scatter) => scatter,
+            None => return color.total(scene.clamp_indirect),
+        };
+
+        scatter_pdf = if specular { None } else { Some(scatter.pdf) };
+        after_diffuse = if specular { after_diffuse } else { !rec.mat.is_volumetric() };
+        throughput *= at_wavelength(scatter.attenuation, ray.wavelength);
+        ray = scatter.ray;
+
+        if roulette_depth.map_or(false, |min_depth| depth >= min_depth) {
+            let survival = throughput.max_element(0.).min(0.95);
+            if rng.gen::<f32>() >= survival {
+                return color.total(scene.clamp_indirect)
+            }
+            throughput /= survival;
+        }
+    }
+
+    color.add(scene.max_depth(), throughput * background(&ray, scene, scatter_pdf));
+    color.total(scene.clamp_indirect)
+}
+
+// Light a path brings to the camera, split on whether it bounced off more
+// than the first surface hit on its way
+#[derive(Default)]
+struct PathRadiance {
+    direct: Vec3,
+    indirect: Vec3,
+}
+
+impl PathRadiance {
+    // Radiance of light having bounced off `bounces` surfaces
+    fn add(&mut self, bounces: usize, radiance: Vec3) {
+        if bounces <= 1 {
+            self.direct += radiance;
+        } else {
+            self.indirect += radiance;
+        }
+    }
+
+    // Scales the indirect light down to `clamp` at most, keeping its hue
+    fn total(self, clamp: Option<f32>) -> Vec3 {
+        let brightest = self.indirect.max_element(0.);
+
+        match clamp {
+            Some(clamp) if brightest > clamp => self.direct + self.indirect * (clamp / brightest),
+            _ => self.direct + self.indirect,
+        }
+    }
+}
+
+// Radiance of the environment reaching the end of the path along `ray`,
+// weighted against sampling it directly after non-specular bounces
+fn background(ray: &Ray, scene: &Scene<impl Hit>, scatter_pdf: Option<f32>) -> Vec3 {
+    let weight = match (&scene.environment, scatter_pdf) {
+        (Some(environment), Some(pdf)) => power_heuristic(pdf, environment.pdf(ray.direction.unit())),
+        _ => 1.,
+    };
+
+    at_wavelength(scene.background(ray.direction), ray.wavelength) * weight
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hit::Sphere;
+    use crate::integrator::tests::{assert_close, mean_radiance, scene_of};
+    use crate::material::{Lambertian, MaterialBuilderExt};
+    use crate::prelude::{Hit, HitRecord, MaterialBuilder, Ray, AABB};
+    use crate::world;
+
+    // Hides the emitters of `T`, which paths then only find by bouncing into them
+    struct UnsampledEmitters<T>(T);
+
+    impl<T: Hit> Hit for UnsampledEmitters<T> {
+        fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+            self.0.hit(ray, t_min, t_max)
+        }
+
+        fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+            self.0.bounding_box(t0, t1)
+        }
+    }
+
+    // A diffuse plane under a spherical light
+    fn lit_plane() -> impl Hit {
+        world![
+            Sphere::builder().center((0, -100, 0)).radius(100).material(Lambertian::colored((0.5, 0.5, 0.5))),
+            Sphere::builder().center((0.5, 2., 0.5)).radius(0.5).diffuse_color((4., 4., 4.)),
+        ]
+    }
+
+    #[test]
+    fn emitter_sampling_converges_to_bsdf_sampling() {
+        // Both estimates stay within about 3% of each other whatever the seed
+        let reference = mean_radiance(&scene_of(UnsampledEmitters(lit_plane()), 256));
+        assert_close(mean_radiance(&scene_of(lit_plane(), 256)), reference, 0.06, "emitter sampling");
+    }
+}
@@ -0,0 +1,160 @@
+use crate::prelude::{Hit, HitRecord, Ray, Vec3};
+use crate::utils::{random_in_unit_sphere, Rng};
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+type Cell = (i32, i32, i32);
+
+#[derive(Debug, Clone)]
+struct Photon {
+    p: Vec3,
+    normal: Vec3,
+    // Direction the photon arrived along
+    incoming: Vec3,
+    power: Vec3,
+}
+
+// Photons which reached a diffuse surface through at least one specular
+// bounce, hashed in a grid of `radius` sized cells
+#[derive(Debug, Clone)]
+pub struct PhotonMap {
+    cells: HashMap<Cell, Vec<Photon>>,
+    radius: f32,
+}
+
+impl PhotonMap {
+    pub fn new(world: &impl Hit, photons: usize, radius: f32, max_depth: usize, mut rng: impl Rng) -> Self {
+        let mut map = Self { cells: HashMap::new(), radius };
+
+        for _ in 0..photons {
+            let (rec, pdf) = match world.sample_emitter_surface(0., &mut rng) {
+                Some((rec, pdf)) if pdf > 0. => (rec, pdf),
+                _ => continue,
+            };
+
+            // Two-sided cosine emission, so the cosine cancels out with the density
+            let normal = if rng.gen::<bool>() { rec.normal } else { -rec.normal };
+            let power = rec.mat.emitted(rec.u, rec.v, rec.p) * (2. * PI / (pdf * photons as f32));
+            let ray = Ray {
+                origin: rec.p,
+                direction: normal + random_in_unit_sphere(&mut rng),
+                time: 0.,
+                wavelength: None,
+                seed: rng.gen(),
+            };
+
+            map.trace(world, ray, power, max_depth, &mut rng);
+        }
+
+        map
+    }
+
+    fn trace(&mut self, world: &impl Hit, mut ray: Ray, mut power: Vec3, max_depth: usize, rng: &mut impl Rng) {
+        for depth in 0..max_depth {
+            let rec = match world.hit(&ray, 0.001, std::f32::MAX) {
+                Some(rec) => rec,
+                None => return,
+            };
+
+            if !rec.mat.is_specular() {
+                // Direct lighting is left to the path tracer
+                if depth > 0 && !rec.mat.is_volumetric() {
+                    let photon = Photon { p: rec.p, normal: rec.normal, incoming: ray.direction, power };
+                    self.cells.entry(self.cell(rec.p)).or_default().push(photon);
+                }
+                return
+            }
+
+            let scatter = match rec.mat.scatter(&ray, &rec, rng) {
+                Some(scatter) => scatter,
+                None => return,
+            };
+
+            power *= scatter.attenuation;
+            ray = scatter.ray;
+        }
+    }
+
+    fn cell(&self, p: Vec3) -> Cell {
+        let cell = p / self.radius;
+        (cell.x().floor() as i32, cell.y().floor() as i32, cell.z().floor() as i32)
+    }
+
+    // Density estimate of the caustics leaving `rec` back along `ray`
+    pub fn radiance(&self, ray: &Ray, rec: &HitRecord) -> Vec3 {
+        let (x, y, z) = self.cell(rec.p);
+        let radius_squared = self.radius * self.radius;
+        let mut summed = Vec3::splat(0.);
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let photons = match self.cells.get(&(x + dx, y + dy, z + dz)) {
+                        Some(photons) => photons,
+                        None => continue,
+                    };
+
+                    for photon in photons {
+                        // Skip photons on nearby surfaces facing elsewhere
+                        if (photon.p - rec.p).squared_len() > radius_squared || Vec3::dot(photon.normal, rec.normal) < 0.9 {
+                            continue
+                        }
+
+                        // `eval` includes the cosine towards the photon, which its power already accounts for
+                        let cosine = Vec3::dot(rec.normal, photon.incoming.unit()).abs().max(1e-4);
+                        summed += rec.mat.eval(rec, ray.direction, -photon.incoming) * photon.power / cosine;
+                    }
+                }
+            }
+        }
+
+        summed / (PI * radius_squared)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::CameraBuilder;
+    use crate::hit::{RectBuilder, Sphere};
+    use crate::integrator::Integrator;
+    use crate::integrator::tests::{assert_close, mean_radiance};
+    use crate::material::{Lambertian, MaterialBuilderExt, Metal};
+    use crate::prelude::{Hit, MaterialBuilder, Vec3};
+    use crate::scene::Scene;
+    use crate::utils::{Pcg32, SeedableRng};
+    use crate::world;
+
+    // A floor in the shadow of a black shade, only lit by a mirror wall
+    // reflecting the light above the shade. The camera looks at the shadow,
+    // so caustics make up the whole image
+    fn shadowed_floor(samples_per_px: u32) -> Scene<impl Hit> {
+        let (width, height) = (8, 8);
+        let camera = CameraBuilder::default()
+            .look_from((0., 2., -3.5))
+            .look_at((0., 0., 0.))
+            .dimensions(width as f32, height as f32)
+            .finish();
+        let world = world![
+            RectBuilder.x(-4..=4).z(-4..=4).y(0).material(Lambertian::colored((0.5, 0.5, 0.5))),
+            RectBuilder.x(-2..=2).z(-2..=2).y(4).material(Lambertian::colored((0., 0., 0.))),
+            RectBuilder.y(0..=8).z(-4..=4).x(3).material(Metal::new(Vec3::splat(0.9), 0.)).flip_normals(),
+            Sphere::builder().center((0, 6, 0)).radius(1).diffuse_color((10., 10., 10.)),
+        ];
+
+        Scene { seed: 7, ..Scene::new(camera, width, height, world, samples_per_px, 8) }
+    }
+
+    #[test]
+    fn converges_to_path_tracing() {
+        let mut scene = shadowed_floor(2048);
+        let reference = mean_radiance(&scene);
+
+        // Within about 10% of the path traced caustics whatever the seeds,
+        // a photon map missing them rendering black
+        scene.samples_per_px = 64;
+        let rng = Pcg32::seed_from_u64(0);
+        scene.integrator = Integrator::photon_mapping(&scene.world, 200_000, 0.1, scene.max_depth(), rng);
+        assert_close(mean_radiance(&scene), reference, 0.2, "photon mapping");
+    }
+}
@@ -0,0 +1,46 @@
+use crate::prelude::{Hit, Ray, Vec3};
+use crate::scene::Scene;
+use crate::spectrum::at_wavelength;
+use crate::utils::Rng;
+use super::lighting::{direct_lighting, environment_lighting};
+
+// Follows perfect specular bounces until a diffuse surface, lit only by
+// emitters and the environment it sees directly. Surfaces are also given
+// their albedo times `ambiant_color` when there is no environment
+pub fn radiance(mut ray: Ray, scene: &Scene<impl Hit>, mut rng: impl Rng) -> Vec3 {
+    let world = &scene.world;
+    let sample_emitters = world.emitter_count() > 0;
+
+    let mut color = Vec3::splat(0.);
+    let mut throughput = Vec3::splat(1.);
+
+    for _ in 0..scene.max_depth() {
+        let rec = match world.hit(&ray, 0.001, std::f32::MAX) {
+            Some(rec) => rec,
+            None => return color + throughput * at_wavelength(scene.background(ray.direction), ray.wavelength),
+        };
+
+        if rec.mat.is_emissive() {
+            color += throughput * at_wavelength(rec.mat.emitted(rec.u, rec.v, rec.p), ray.wavelength);
+        }
+
+        if let Some(scatter) = rec.mat.specular_scatter(&ray, &rec, &mut rng) {
+            throughput *= at_wavelength(scatter.attenuation, ray.wavelength);
+            ray = scatter.ray;
+            continue
+        }
+
+        if sample_emitters {
+            color += throughput * direct_lighting(&ray, &rec, world, false, &mut rng);
+        }
+
+        color += throughput * match &scene.environment {
+            Some(environment) => environment_lighting(&ray, &rec, world, &**environment, false, &mut rng),
+            None => at_wavelength(rec.mat.albedo(&rec) * scene.ambiant_color, ray.wavelength),
+        };
+
+        return color
+    }
+
+    color
+}
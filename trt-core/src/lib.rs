@@ -3,15 +3,29 @@
 
 mod utils;
 
+pub mod accumulator;
+pub mod adaptive;
+pub mod aov;
 pub mod aabb;
 pub mod camera;
 pub mod color;
+pub mod debug;
+pub mod denoise;
+pub mod density;
 pub mod dimension;
+pub mod environment;
+pub mod film;
+pub mod framebuffer;
 pub mod hit;
+pub mod integrator;
 pub mod material;
 pub mod perlin;
 pub mod prelude;
 pub mod ray;
+pub mod sampler;
 pub mod scene;
+pub mod spectrum;
 pub mod texture;
+pub mod tile;
+pub mod tonemap;
 pub mod vec3;
@@ -0,0 +1,52 @@
+use crate::prelude::{Texture, Material, HitRecord, Ray, Vec3};
+use crate::material::{ScatterRecord, PhaseFunction};
+use crate::utils::RngCore;
+
+// Like `Isotropic`, scattering light around according to `phase` instead
+pub struct Anisotropic<T: Texture> {
+    albedo: T,
+    phase: PhaseFunction,
+}
+
+impl<T: Texture> Anisotropic<T> {
+    pub fn new(albedo: T, phase: PhaseFunction) -> Self {
+        Self { albedo, phase }
+    }
+
+    fn cos_theta(incoming: Vec3, outgoing: Vec3) -> f32 {
+        Vec3::dot(incoming.unit(), outgoing.unit())
+    }
+}
+
+impl<T: Texture> Material for Anisotropic<T> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        let direction = self.phase.sample(r_in.direction, rng);
+        let scattered = Ray {
+            origin: rec.p,
+            direction,
+            time: r_in.time,
+            wavelength: r_in.wavelength,
+            seed: r_in.seed,
+        };
+        // The phase function is sampled exactly, leaving only the albedo
+        let attenuation = self.albedo.value(rec.u, rec.v, rec.p);
+        let pdf = self.phase.eval(Self::cos_theta(r_in.direction, direction));
+        Some(ScatterRecord { ray: scattered, attenuation, pdf })
+    }
+
+    fn is_volumetric(&self) -> bool {
+        true
+    }
+
+    fn eval(&self, rec: &HitRecord, incoming: Vec3, outgoing: Vec3) -> Vec3 {
+        self.albedo.value(rec.u, rec.v, rec.p) * self.phase.eval(Self::cos_theta(incoming, outgoing))
+    }
+
+    fn scattering_pdf(&self, _rec: &HitRecord, incoming: Vec3, outgoing: Vec3) -> f32 {
+        self.phase.eval(Self::cos_theta(incoming, outgoing))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.albedo.value(rec.u, rec.v, rec.p)
+    }
+}
@@ -1,5 +1,5 @@
 use crate::prelude::{Vec3, Asf32};
-use crate::material::{Metal, Dielectric, Diffuse, Lambertian};
+use crate::material::{Metal, Dielectric, Dispersion, Diffuse, Lambertian};
 use crate::texture::Constant;
 
 pub trait MaterialBuilder<Mat>: Sized {
@@ -16,6 +16,13 @@ pub trait MaterialBuilderExt {
         self.material(Dielectric::new(ref_idx))
     }
 
+    fn dispersive(self, dispersion: Dispersion) -> Self::Finished
+    where
+        Self: MaterialBuilder<Dielectric>,
+    {
+        self.material(Dielectric::dispersive(dispersion))
+    }
+
     fn diffuse_color(self, color: impl Into<Vec3>) -> Self::Finished
     where
         Self: MaterialBuilder<Diffuse<Constant>>,
@@ -1,48 +1,110 @@
 use crate::prelude::{Material, HitRecord, Ray, Vec3};
-use crate::utils::{reflect, refract, schlick};
+use crate::material::ScatterRecord;
+use crate::utils::{reflect, refract, schlick, Rng, RngCore};
+
+// Wavelength dependent refractive index, with wavelengths in micrometers
+#[derive(Debug, Clone, Copy)]
+pub enum Dispersion {
+    // n = a + b / λ²
+    Cauchy { a: f32, b: f32 },
+    // n² = 1 + Σ bᵢλ² / (λ² - cᵢ)
+    Sellmeier { b: [f32; 3], c: [f32; 3] },
+}
+
+impl Dispersion {
+    pub fn ref_idx(&self, wavelength: f32) -> f32 {
+        let micrometers = wavelength / 1000.;
+        let squared = micrometers * micrometers;
+
+        match *self {
+            Dispersion::Cauchy { a, b } => a + b / squared,
+            Dispersion::Sellmeier { b, c } => {
+                let summed = (0..3).map(|i| b[i] * squared / (squared - c[i])).sum::<f32>();
+                (1. + summed).sqrt()
+            },
+        }
+    }
+}
 
 pub struct Dielectric {
     ref_idx: f32,
+    dispersion: Option<Dispersion>,
 }
 
 impl Dielectric {
     pub fn new(ref_idx: f32) -> Self {
-        Self { ref_idx }
+        Self { ref_idx, dispersion: None }
+    }
+
+    // Outside of spectral rendering the index at the sodium d-line is used
+    pub fn dispersive(dispersion: Dispersion) -> Self {
+        Self { ref_idx: dispersion.ref_idx(587.6), dispersion: Some(dispersion) }
+    }
+
+    fn ref_idx(&self, wavelength: Option<f32>) -> f32 {
+        match (self.dispersion, wavelength) {
+            (Some(dispersion), Some(wavelength)) => dispersion.ref_idx(wavelength),
+            _ => self.ref_idx,
+        }
     }
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
         let reflected = reflect(r_in.direction, rec.normal);
         let attenuation = Vec3::splat(1.);
+        let ref_idx = self.ref_idx(r_in.wavelength);
 
         let (outward_normal, ni_over_nt, cosine) =
             if Vec3::dot(r_in.direction, rec.normal) > 0. {
-                let cosine = self.ref_idx * Vec3::dot(r_in.direction, rec.normal) / r_in.direction.len();
-                (-rec.normal, self.ref_idx, cosine)
+                let cosine = ref_idx * Vec3::dot(r_in.direction, rec.normal) / r_in.direction.len();
+                (-rec.normal, ref_idx, cosine)
             } else {
                 let cosine = -Vec3::dot(r_in.direction, rec.normal) / r_in.direction.len();
-                (rec.normal, 1.0 / self.ref_idx, cosine)
+                (rec.normal, 1.0 / ref_idx, cosine)
             };
 
-        let prob = rand::random::<f32>();
-
-        if let Some(refracted) = refract(r_in.direction, outward_normal, ni_over_nt) {
-            if prob >= schlick(cosine, self.ref_idx) {
-                let scattered = Ray {
-                    origin: rec.p,
-                    direction: refracted,
-                    time: 0.,
-                };
-                return Some((scattered, attenuation))
-            }
-        }
+        let prob = rng.gen::<f32>();
+
+        let reflect_prob = match refract(r_in.direction, outward_normal, ni_over_nt) {
+            Some(refracted) => {
+                let reflect_prob = schlick(cosine, ref_idx);
+                if prob >= reflect_prob {
+                    let scattered = Ray {
+                        origin: rec.p,
+                        direction: refracted,
+                        time: r_in.time,
+                        wavelength: r_in.wavelength,
+                        seed: r_in.seed,
+                    };
+                    return Some(ScatterRecord { ray: scattered, attenuation, pdf: 1. - reflect_prob })
+                }
+                reflect_prob
+            },
+            None => 1.,
+        };
 
         let scattered = Ray {
             origin: rec.p,
             direction: reflected,
-            time: 0.,
+            time: r_in.time,
+            wavelength: r_in.wavelength,
+            seed: r_in.seed,
         };
-        Some((scattered, attenuation))
+        Some(ScatterRecord { ray: scattered, attenuation, pdf: reflect_prob })
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    // Reflection and refraction are picked at random, in proportion to the
+    // light each one carries
+    fn specular_scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        self.scatter(r_in, rec, rng)
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Vec3 {
+        Vec3::splat(1.)
     }
 }
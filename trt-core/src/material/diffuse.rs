@@ -1,5 +1,7 @@
 use crate::prelude::{Texture, Material, HitRecord, Ray, Vec3};
+use crate::material::ScatterRecord;
 use crate::texture::Constant;
+use crate::utils::RngCore;
 
 pub struct Diffuse<T> {
     emit: T,
@@ -20,11 +22,15 @@ impl Diffuse<UnboundedTx> {
 }
 
 impl<T: Texture> Material for Diffuse<T> {
-    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Vec3)> {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _rng: &mut dyn RngCore) -> Option<ScatterRecord> {
         None
     }
 
     fn emitted(&self, u: f32, v: f32, p: Vec3) -> Vec3 {
         self.emit.value(u, v, p)
     }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
 }
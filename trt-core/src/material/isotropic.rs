@@ -1,5 +1,9 @@
 use crate::prelude::{Texture, Material, HitRecord, Ray, Vec3};
-use crate::utils::random_in_unit_sphere;
+use crate::material::ScatterRecord;
+use crate::utils::{random_in_unit_sphere, RngCore};
+
+use std::f32::consts::PI;
+
 pub struct Isotropic<T: Texture> {
     albedo: T
 }
@@ -11,13 +15,31 @@ impl<T: Texture> Isotropic<T> {
 }
 
 impl<T: Texture> Material for Isotropic<T> {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
         let scattered = Ray {
             origin: rec.p,
-            direction: random_in_unit_sphere(rand::thread_rng()),
+            direction: random_in_unit_sphere(rng),
             time: r_in.time,
+            wavelength: r_in.wavelength,
+            seed: r_in.seed,
         };
         let attenuation = self.albedo.value(rec.u, rec.v, rec.p);
-        Some((scattered, attenuation))
+        Some(ScatterRecord { ray: scattered, attenuation, pdf: 1. / (4. * PI) })
+    }
+
+    fn is_volumetric(&self) -> bool {
+        true
+    }
+
+    fn eval(&self, rec: &HitRecord, _incoming: Vec3, _outgoing: Vec3) -> Vec3 {
+        self.albedo.value(rec.u, rec.v, rec.p) / (4. * PI)
+    }
+
+    fn scattering_pdf(&self, _rec: &HitRecord, _incoming: Vec3, _outgoing: Vec3) -> f32 {
+        1. / (4. * PI)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.albedo.value(rec.u, rec.v, rec.p)
     }
 }
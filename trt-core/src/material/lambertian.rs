@@ -1,6 +1,9 @@
 use crate::prelude::{Texture, Material, HitRecord, Ray, Vec3};
+use crate::material::ScatterRecord;
 use crate::texture::Constant;
-use crate::utils::random_in_unit_sphere;
+use crate::utils::{random_in_unit_sphere, RngCore};
+
+use std::f32::consts::PI;
 
 pub struct Lambertian<T> {
     albedo: T,
@@ -21,14 +24,31 @@ impl Lambertian<UnboundedTx> {
 }
 
 impl<T: Texture> Material for Lambertian<T> {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
-        let target = rec.p + rec.normal + random_in_unit_sphere(rand::thread_rng());
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        let target = rec.p + rec.normal + random_in_unit_sphere(rng);
         let scattered = Ray {
             origin: rec.p,
             direction: target - rec.p,
             time: r_in.time,
+            wavelength: r_in.wavelength,
+            seed: r_in.seed,
         };
+        let pdf = self.scattering_pdf(rec, r_in.direction, scattered.direction);
         let attenuation = self.albedo.value(rec.u, rec.v, rec.p);
-        Some((scattered, attenuation))
+        Some(ScatterRecord { ray: scattered, attenuation, pdf })
+    }
+
+    fn eval(&self, rec: &HitRecord, incoming: Vec3, outgoing: Vec3) -> Vec3 {
+        let albedo = self.albedo.value(rec.u, rec.v, rec.p);
+        albedo * self.scattering_pdf(rec, incoming, outgoing)
+    }
+
+    fn scattering_pdf(&self, rec: &HitRecord, _incoming: Vec3, outgoing: Vec3) -> f32 {
+        let cosine = Vec3::dot(rec.normal, outgoing.unit());
+        cosine.max(0.) / PI
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.albedo.value(rec.u, rec.v, rec.p)
     }
 }
@@ -1,5 +1,8 @@
 use crate::prelude::{Vec3, Material, Ray, HitRecord};
-use crate::utils::{reflect, random_in_unit_sphere};
+use crate::material::ScatterRecord;
+use crate::utils::{reflect, random_in_unit_sphere, RngCore};
+
+use std::f32::consts::PI;
 
 pub struct Metal {
     albedo: Vec3,
@@ -13,21 +16,74 @@ impl Metal {
             fuzz: fuzz.min(1.),
         }
     }
+
+    // Scattered directions are `reflected + fuzz * s` with `s` uniform on the
+    // unit sphere: every direction `d` is reached through the (up to) two
+    // points of that fuzz sphere lying on the line spanned by `d`
+    fn fuzz_pdf(&self, reflected: Vec3, direction: Vec3) -> f32 {
+        let cosine = Vec3::dot(reflected, direction.unit());
+        let discriminant = cosine * cosine - (1. - self.fuzz * self.fuzz);
+
+        if cosine <= 0. || discriminant <= 0. {
+            return 0.
+        }
+
+        let disc_sqrt = discriminant.sqrt();
+        let far = cosine + disc_sqrt;
+        let near = (cosine - disc_sqrt).max(0.);
+
+        (far * far + near * near) / (4. * PI * self.fuzz * disc_sqrt)
+    }
 }
 
 impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
         let reflected = reflect(r_in.direction.unit(), rec.normal);
         let scattered = Ray {
             origin: rec.p,
-            direction: reflected + self.fuzz * random_in_unit_sphere(rand::thread_rng()),
-            time: 0.
+            direction: reflected + self.fuzz * random_in_unit_sphere(rng),
+            time: r_in.time,
+            wavelength: r_in.wavelength,
+            seed: r_in.seed,
         };
         let attenuation = self.albedo;
         if Vec3::dot(scattered.direction, rec.normal) > 0. {
-            Some((scattered, attenuation))
+            let pdf = if self.is_specular() { 1. } else { self.fuzz_pdf(reflected, scattered.direction) };
+            Some(ScatterRecord { ray: scattered, attenuation, pdf })
         } else {
             None
         }
     }
+
+    fn is_specular(&self) -> bool {
+        self.fuzz <= 0.
+    }
+
+    fn specular_scatter(&self, r_in: &Ray, rec: &HitRecord, _rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        let scattered = Ray {
+            origin: rec.p,
+            direction: reflect(r_in.direction.unit(), rec.normal),
+            time: r_in.time,
+            wavelength: r_in.wavelength,
+            seed: r_in.seed,
+        };
+        Some(ScatterRecord { ray: scattered, attenuation: self.albedo, pdf: 1. })
+    }
+
+    fn eval(&self, rec: &HitRecord, incoming: Vec3, outgoing: Vec3) -> Vec3 {
+        self.albedo * self.scattering_pdf(rec, incoming, outgoing)
+    }
+
+    fn scattering_pdf(&self, rec: &HitRecord, incoming: Vec3, outgoing: Vec3) -> f32 {
+        if self.is_specular() || Vec3::dot(outgoing, rec.normal) <= 0. {
+            return 0.
+        }
+
+        let reflected = reflect(incoming.unit(), rec.normal);
+        self.fuzz_pdf(reflected, outgoing)
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Vec3 {
+        self.albedo
+    }
 }
@@ -1,37 +1,128 @@
 use crate::prelude::{Ray, HitRecord, Vec3};
+use crate::utils::RngCore;
 use std::sync::Arc;
 use std::rc::Rc;
 
+pub struct ScatterRecord {
+    pub ray: Ray,
+    // BSDF times cosine over pdf, i.e. the path throughput factor of `ray`
+    pub attenuation: Vec3,
+    // Solid angle density of `ray.direction`, or the discrete probability of
+    // the chosen lobe for specular materials
+    pub pdf: f32,
+}
+
 pub trait Material {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)>;
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord>;
     fn emitted(&self, _u: f32, _v: f32, _p: Vec3) -> Vec3 {
         Vec3::splat(0.)
     }
+    fn is_emissive(&self) -> bool {
+        false
+    }
+    // Specular materials scatter along discrete directions: `eval` and
+    // `scattering_pdf` are zero everywhere for them
+    fn is_specular(&self) -> bool {
+        false
+    }
+    // Volumetric materials (phase functions) have no cosine foreshortening
+    fn is_volumetric(&self) -> bool {
+        false
+    }
+    // BSDF times cosine for light arriving along `incoming` and leaving along `outgoing`
+    fn eval(&self, _rec: &HitRecord, _incoming: Vec3, _outgoing: Vec3) -> Vec3 {
+        Vec3::splat(0.)
+    }
+    // Solid angle density of `scatter` picking `outgoing` for a ray arriving along `incoming`
+    fn scattering_pdf(&self, _rec: &HitRecord, _incoming: Vec3, _outgoing: Vec3) -> f32 {
+        0.
+    }
+    // Perfect mirror or glass scattering for Whitted-style tracing, `None`
+    // for materials only scattering diffusely. Metals ignore their fuzz there
+    fn specular_scatter(&self, _r_in: &Ray, _rec: &HitRecord, _rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        None
+    }
+    // Fraction of light reflected at the hit point, for albedo buffers
+    fn albedo(&self, _rec: &HitRecord) -> Vec3 {
+        Vec3::splat(0.)
+    }
+    // Identifies the material in material id buffers, shared pointers to it included
+    fn id(&self) -> usize {
+        self as *const Self as *const () as usize
+    }
 }
 
 impl<T: Material + ?Sized> Material for Arc<T> {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
-        self.as_ref().scatter(r_in, rec)
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        self.as_ref().scatter(r_in, rec, rng)
     }
     fn emitted(&self, u: f32, v: f32, p: Vec3) -> Vec3 {
         self.as_ref().emitted(u, v, p)
     }
+    fn is_emissive(&self) -> bool {
+        self.as_ref().is_emissive()
+    }
+    fn is_specular(&self) -> bool {
+        self.as_ref().is_specular()
+    }
+    fn is_volumetric(&self) -> bool {
+        self.as_ref().is_volumetric()
+    }
+    fn eval(&self, rec: &HitRecord, incoming: Vec3, outgoing: Vec3) -> Vec3 {
+        self.as_ref().eval(rec, incoming, outgoing)
+    }
+    fn scattering_pdf(&self, rec: &HitRecord, incoming: Vec3, outgoing: Vec3) -> f32 {
+        self.as_ref().scattering_pdf(rec, incoming, outgoing)
+    }
+    fn specular_scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        self.as_ref().specular_scatter(r_in, rec, rng)
+    }
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.as_ref().albedo(rec)
+    }
+    fn id(&self) -> usize {
+        self.as_ref().id()
+    }
 }
 
 impl<T: Material + ?Sized> Material for Rc<T> {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
-        self.as_ref().scatter(r_in, rec)
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        self.as_ref().scatter(r_in, rec, rng)
     }
     fn emitted(&self, u: f32, v: f32, p: Vec3) -> Vec3 {
         self.as_ref().emitted(u, v, p)
     }
+    fn is_emissive(&self) -> bool {
+        self.as_ref().is_emissive()
+    }
+    fn is_specular(&self) -> bool {
+        self.as_ref().is_specular()
+    }
+    fn is_volumetric(&self) -> bool {
+        self.as_ref().is_volumetric()
+    }
+    fn eval(&self, rec: &HitRecord, incoming: Vec3, outgoing: Vec3) -> Vec3 {
+        self.as_ref().eval(rec, incoming, outgoing)
+    }
+    fn scattering_pdf(&self, rec: &HitRecord, incoming: Vec3, outgoing: Vec3) -> f32 {
+        self.as_ref().scattering_pdf(rec, incoming, outgoing)
+    }
+    fn specular_scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut dyn RngCore) -> Option<ScatterRecord> {
+        self.as_ref().specular_scatter(r_in, rec, rng)
+    }
+    fn albedo(&self, rec: &HitRecord) -> Vec3 {
+        self.as_ref().albedo(rec)
+    }
+    fn id(&self) -> usize {
+        self.as_ref().id()
+    }
 }
 
 mod metal;
 pub use metal::Metal;
 
 mod dielectric;
-pub use dielectric::Dielectric;
+pub use dielectric::{Dielectric, Dispersion};
 
 mod lambertian;
 pub use lambertian::Lambertian;
@@ -42,5 +133,11 @@ pub use diffuse::Diffuse;
 mod isotropic;
 pub use isotropic::Isotropic;
 
+mod anisotropic;
+pub use anisotropic::Anisotropic;
+
+mod phase;
+pub use phase::PhaseFunction;
+
 pub mod builder;
 pub use builder::{MaterialBuilder, MaterialBuilderExt};
@@ -0,0 +1,127 @@
+use crate::prelude::Vec3;
+use crate::utils::{Onb, Rng, RngCore};
+
+use std::f32::consts::PI;
+
+// How light scattered inside a participating medium spreads around the
+// direction it was going, by the cosine of the angle it is deflected by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PhaseFunction {
+    Isotropic,
+    // Asymmetry from -1 scattering everything backwards to 1 letting it go
+    // straight through, 0 being isotropic. Haze is about 0.7
+    HenyeyGreenstein(f32),
+    // Particles much smaller than the wavelength, like air molecules
+    Rayleigh,
+    // A `weight` share of the light scattered by the `forward` lobe, the rest
+    // by the `backward` one, like the bright rims and back glow of clouds
+    DoubleHenyeyGreenstein { forward: f32, backward: f32, weight: f32 },
+}
+
+impl PhaseFunction {
+    // Solid angle density of scattering `cos_theta` away from the incoming direction
+    pub fn eval(&self, cos_theta: f32) -> f32 {
+        match *self {
+            PhaseFunction::Isotropic => 1. / (4. * PI),
+            PhaseFunction::HenyeyGreenstein(g) => henyey_greenstein(g, cos_theta),
+            PhaseFunction::Rayleigh => 3. / (16. * PI) * (1. + cos_theta * cos_theta),
+            PhaseFunction::DoubleHenyeyGreenstein { forward, backward, weight } => {
+                weight * henyey_greenstein(forward, cos_theta) + (1. - weight) * henyey_greenstein(backward, cos_theta)
+            },
+        }
+    }
+
+    // Unit direction following `eval` around `incoming`
+    pub fn sample(&self, incoming: Vec3, rng: &mut dyn RngCore) -> Vec3 {
+        let cos_theta = match *self {
+            PhaseFunction::Isotropic => 1. - 2. * rng.gen::<f32>(),
+            PhaseFunction::HenyeyGreenstein(g) => sample_henyey_greenstein(g, rng.gen()),
+            PhaseFunction::Rayleigh => {
+                // Solves the cubic inverse of the cdf (cos³ + 3 cos + 4) / 8 with Cardano's formula
+                let z = 4. * rng.gen::<f32>() - 2.;
+                let u = (z + (z * z + 1.).sqrt()).cbrt();
+                u - 1. / u
+            },
+            PhaseFunction::DoubleHenyeyGreenstein { forward, backward, weight } => {
+                let g = if rng.gen::<f32>() < weight { forward } else { backward };
+                sample_henyey_greenstein(g, rng.gen())
+            },
+        };
+
+        let cos_theta = cos_theta.max(-1.).min(1.);
+        let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+        let phi = 2. * PI * rng.gen::<f32>();
+
+        Onb::from_w(incoming).local(Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta))
+    }
+}
+
+impl Default for PhaseFunction {
+    fn default() -> Self {
+        PhaseFunction::Isotropic
+    }
+}
+
+// At ±1 the lobe would be a Dirac delta, without a density to evaluate
+fn clamp_asymmetry(g: f32) -> f32 {
+    g.max(-0.999).min(0.999)
+}
+
+fn henyey_greenstein(g: f32, cos_theta: f32) -> f32 {
+    let g = clamp_asymmetry(g);
+    let denominator = 1. + g * g - 2. * g * cos_theta;
+    (1. - g * g) / (4. * PI * denominator * denominator.max(1e-8).sqrt())
+}
+
+fn sample_henyey_greenstein(g: f32, u: f32) -> f32 {
+    let g = clamp_asymmetry(g);
+    if g.abs() < 1e-3 {
+        return 1. - 2. * u
+    }
+
+    let s = (1. - g * g) / (1. - g + 2. * g * u);
+    (1. + g * g - s * s) / (2. * g)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{Pcg32, SeedableRng};
+
+    #[test]
+    fn extreme_asymmetries_stay_finite() {
+        let mut rng = Pcg32::seed_from_u64(0);
+
+        for &g in &[-1., 1., -2., 2.] {
+            let phase = PhaseFunction::HenyeyGreenstein(g);
+            for &cos_theta in &[-1., 0., 1.] {
+                assert!(phase.eval(cos_theta).is_finite(), "g = {}, cos = {}", g, cos_theta);
+            }
+
+            for _ in 0..100 {
+                let direction = phase.sample(Vec3::new(0., 0., 1.), &mut rng);
+                assert!(direction.x().is_finite() && direction.y().is_finite() && direction.z().is_finite());
+            }
+        }
+    }
+
+    #[test]
+    fn densities_integrate_to_one() {
+        let phases = [
+            PhaseFunction::Isotropic,
+            PhaseFunction::HenyeyGreenstein(0.7),
+            PhaseFunction::HenyeyGreenstein(-0.3),
+            PhaseFunction::Rayleigh,
+            PhaseFunction::DoubleHenyeyGreenstein { forward: 0.8, backward: -0.4, weight: 0.7 },
+        ];
+
+        for phase in &phases {
+            // Midpoint rule over cos theta, the azimuth contributing 2 pi
+            let steps = 100_000;
+            let integral = (0..steps)
+                .map(|i| phase.eval(-1. + (2 * i + 1) as f32 / steps as f32))
+                .sum::<f32>() * 2. / steps as f32 * 2. * PI;
+            assert!((integral - 1.).abs() < 1e-3, "{:?} integrates to {}", phase, integral);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::utils::{Rng, thread_rng};
+use crate::utils::Rng;
 use crate::vec3::Vec3;
 
 pub struct Perlin {
@@ -9,12 +9,12 @@ pub struct Perlin {
 }
 
 impl Perlin {
-    pub fn new() -> Self {
+    pub fn new(mut rng: impl Rng) -> Self {
         Self {
-            ranvec: perlin_generate(),
-            perm_x: perlin_generate_perm(),
-            perm_y: perlin_generate_perm(),
-            perm_z: perlin_generate_perm(),
+            ranvec: perlin_generate(&mut rng),
+            perm_x: perlin_generate_perm(&mut rng),
+            perm_y: perlin_generate_perm(&mut rng),
+            perm_z: perlin_generate_perm(&mut rng),
         }
     }
 
@@ -56,9 +56,8 @@ impl Perlin {
     }
 }
 
-fn perlin_generate() -> [Vec3; 256] {
+fn perlin_generate(mut rng: impl Rng) -> [Vec3; 256] {
     let mut p = [Vec3::splat(0.); 256];
-    let mut rng = thread_rng();
     for v in &mut p[..] {
         let x = 2. * rng.gen::<f32>() - 1.;
         let y = 2. * rng.gen::<f32>() - 1.;
@@ -68,18 +67,16 @@ fn perlin_generate() -> [Vec3; 256] {
     p
 }
 
-fn perlin_generate_perm() -> [usize; 256] {
+fn perlin_generate_perm(rng: impl Rng) -> [usize; 256] {
     let mut p = [0; 256];
     for (i, x) in p.iter_mut().enumerate() {
         *x = i
     }
-    permute(&mut p[..]);
+    permute(&mut p[..], rng);
     p
 }
 
-fn permute(slice: &mut [usize]) {
-    let mut rng = thread_rng();
-
+fn permute(slice: &mut [usize], mut rng: impl Rng) {
     for i in (1..slice.len()).rev() {
         let target = rng.gen_range(0, i + 1);
         slice.swap(i, target)
@@ -5,6 +5,11 @@ pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
     pub time: f32,
+    // Sampled wavelength in nanometers, when rendering spectrally
+    pub wavelength: Option<f32>,
+    // Drawn from the sampler when a path starts and kept by the rays along
+    // it, to seed the decisions taken while intersecting them
+    pub seed: u64,
 }
 
 impl Ray {
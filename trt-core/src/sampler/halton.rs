@@ -0,0 +1,66 @@
+use super::{random_u32, Independent, Sampler};
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53,
+    59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131,
+];
+
+// Halton sequence, randomized per pixel with a Cranley-Patterson rotation.
+// Dimensions past the first primes are badly distributed and fall back to
+// independent random numbers
+#[derive(Debug, Clone, Copy)]
+pub struct Halton;
+
+impl Sampler for Halton {
+    fn sample(&self, key: u64, index: u32, dimension: u32) -> u32 {
+        let base = match PRIMES.get(dimension as usize) {
+            Some(&base) => base,
+            None => return Independent.sample(key, index, dimension),
+        };
+
+        let rotation = random_u32(&[key, dimension as u64]);
+        let value = (radical_inverse(index, base) * (1u64 << 32) as f64) as u32;
+
+        value.wrapping_add(rotation)
+    }
+}
+
+// Mirrors the digits of `index` written in `base` around the radix point
+fn radical_inverse(mut index: u32, base: u32) -> f64 {
+    let inverse_base = 1. / base as f64;
+    let (mut reversed, mut scale) = (0., inverse_base);
+
+    while index > 0 {
+        reversed += (index % base) as f64 * scale;
+        index /= base;
+        scale *= inverse_base;
+    }
+
+    reversed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radical_inverse_prefixes() {
+        let base_2 = (0..8).map(|i| radical_inverse(i, 2)).collect::<Vec<_>>();
+        assert_eq!(base_2, [0., 0.5, 0.25, 0.75, 0.125, 0.625, 0.375, 0.875]);
+
+        let base_3 = (0..6).map(|i| radical_inverse(i, 3) * 9.).collect::<Vec<_>>();
+        for (value, expected) in base_3.iter().zip(&[0., 3., 6., 1., 4., 7.]) {
+            assert!((value - expected).abs() < 1e-9, "{:?}", base_3);
+        }
+    }
+
+    #[test]
+    fn rotation_is_per_pixel_and_dimension() {
+        // Without the rotation, the first sample would be 0 everywhere
+        assert_ne!(Halton.sample(1, 0, 0), Halton.sample(2, 0, 0));
+        assert_ne!(Halton.sample(1, 0, 0), Halton.sample(1, 0, 1));
+
+        let rotation = Halton.sample(1, 0, 0);
+        assert_eq!(Halton.sample(1, 1, 0), rotation.wrapping_add(1 << 31));
+    }
+}
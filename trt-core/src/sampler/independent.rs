@@ -0,0 +1,11 @@
+use super::{random_u32, Sampler};
+
+// Uncorrelated uniform random numbers
+#[derive(Debug, Clone, Copy)]
+pub struct Independent;
+
+impl Sampler for Independent {
+    fn sample(&self, key: u64, index: u32, dimension: u32) -> u32 {
+        random_u32(&[key, index as u64, dimension as u64])
+    }
+}
@@ -0,0 +1,104 @@
+mod halton;
+mod independent;
+mod sobol;
+mod stratified;
+
+pub use halton::Halton;
+pub use independent::Independent;
+pub use sobol::Sobol;
+pub use stratified::Stratified;
+
+use crate::utils::{hash, RngCore};
+
+use std::sync::Arc;
+
+// Sequences of sample points, handing out one dimension at a time
+pub trait Sampler {
+    // Dimension `dimension` of the `index`th sample of the pixel identified by
+    // `key`, as a fixed point value in [0, 1)
+    fn sample(&self, key: u64, index: u32, dimension: u32) -> u32;
+}
+
+pub type SharedSampler = Arc<dyn Sampler + Send + Sync>;
+
+pub fn from_name(name: &str, samples_per_px: u32) -> Option<SharedSampler> {
+    match name {
+        "independent" => Some(Arc::new(Independent)),
+        "stratified" => Some(Arc::new(Stratified::new(samples_per_px))),
+        "halton" => Some(Arc::new(Halton)),
+        "sobol" => Some(Arc::new(Sobol)),
+        _ => None,
+    }
+}
+
+// Walks through the dimensions of a single pixel sample. Acts as a random
+// number generator so every part of the renderer consumes it unchanged,
+// with each generated `u32` being the next dimension
+pub struct SampleStream<'a> {
+    sampler: &'a dyn Sampler,
+    key: u64,
+    index: u32,
+    dimension: u32,
+}
+
+impl<'a> SampleStream<'a> {
+    pub fn new(sampler: &'a dyn Sampler, key: u64, index: u32) -> Self {
+        Self { sampler, key, index, dimension: 0 }
+    }
+
+    pub fn get_1d(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    pub fn get_2d(&mut self) -> (f32, f32) {
+        let u = self.get_1d();
+        (u, self.get_1d())
+    }
+}
+
+impl RngCore for SampleStream<'_> {
+    fn next_u32(&mut self) -> u32 {
+        let value = self.sampler.sample(self.key, self.index, self.dimension);
+        self.dimension += 1;
+        value
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let high = self.next_u32() as u64;
+        high << 32 | self.next_u32() as u64
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// Uniform value of `values`, for the random parts of the sequences
+fn random_u32(values: &[u64]) -> u32 {
+    (hash(values) >> 32) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::pick;
+
+    #[test]
+    fn picks_take_a_single_dimension() {
+        for &count in &[0, 1, 3, 7, 1000] {
+            for index in 0..64 {
+                let mut stream = SampleStream::new(&Sobol, 5, index);
+                assert!(pick(&mut stream, count) < count.max(1));
+                assert_eq!(stream.dimension, 1);
+            }
+        }
+    }
+}
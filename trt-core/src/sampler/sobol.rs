@@ -0,0 +1,116 @@
+use super::{random_u32, Sampler};
+
+// Primitive polynomials (degree, coefficients, initial direction numbers)
+// of Sobol's dimensions past the first, from Joe and Kuo's tables
+const POLYNOMIALS: [(usize, u32, [u32; 3]); 3] = [
+    (1, 0, [1, 0, 0]),
+    (2, 1, [1, 3, 0]),
+    (3, 1, [1, 3, 1]),
+];
+
+const DIRECTIONS: [[u32; 32]; 4] = directions();
+
+const fn directions() -> [[u32; 32]; 4] {
+    let mut directions = [[0; 32]; 4];
+
+    let mut bit = 0;
+    while bit < 32 {
+        directions[0][bit] = 1 << (31 - bit);
+        bit += 1;
+    }
+
+    let mut dimension = 1;
+    while dimension < 4 {
+        let (degree, coefficients, initial) = POLYNOMIALS[dimension - 1];
+        let mut v = [0; 32];
+
+        let mut bit = 0;
+        while bit < 32 {
+            if bit < degree {
+                v[bit] = initial[bit] << (31 - bit);
+            } else {
+                v[bit] = v[bit - degree] ^ (v[bit - degree] >> degree);
+                let mut k = 1;
+                while k < degree {
+                    v[bit] ^= ((coefficients >> (degree - 1 - k)) & 1) * v[bit - k];
+                    k += 1;
+                }
+            }
+            bit += 1;
+        }
+
+        directions[dimension] = v;
+
+        dimension += 1;
+    }
+
+    directions
+}
+
+// Owen scrambled Sobol sequence, following Burley's "Practical Hash-based
+// Owen Scrambling" (2020): dimensions are drawn 4 at a time from shuffled
+// and scrambled copies of a 4D Sobol sequence
+#[derive(Debug, Clone, Copy)]
+pub struct Sobol;
+
+impl Sampler for Sobol {
+    fn sample(&self, key: u64, index: u32, dimension: u32) -> u32 {
+        let group = dimension / 4;
+        let shuffled = nested_uniform_scramble(index, random_u32(&[key, group as u64]));
+        let value = sobol(shuffled, (dimension % 4) as usize);
+
+        nested_uniform_scramble(value, random_u32(&[key, dimension as u64, 1]))
+    }
+}
+
+fn sobol(index: u32, dimension: usize) -> u32 {
+    (0..32)
+        .filter(|bit| index >> bit & 1 == 1)
+        .fold(0, |value, bit| value ^ DIRECTIONS[dimension][bit])
+}
+
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefix(dimension: usize) -> Vec<f64> {
+        (0..8).map(|i| sobol(i, dimension) as f64 / (1u64 << 32) as f64).collect()
+    }
+
+    // In index order rather than Gray code order
+    #[test]
+    fn unscrambled_prefixes() {
+        assert_eq!(prefix(0), [0., 0.5, 0.25, 0.75, 0.125, 0.625, 0.375, 0.875]);
+        assert_eq!(prefix(1), [0., 0.5, 0.75, 0.25, 0.625, 0.125, 0.375, 0.875]);
+        assert_eq!(prefix(2), [0., 0.5, 0.75, 0.25, 0.375, 0.875, 0.625, 0.125]);
+        assert_eq!(prefix(3), [0., 0.5, 0.75, 0.25, 0.125, 0.625, 0.875, 0.375]);
+    }
+
+    // Owen scrambling keeps the first 2^k samples in distinct intervals of
+    // width 2^-k, in every dimension of every pixel
+    #[test]
+    fn pixels_are_stratified() {
+        for key in 0..16 {
+            for dimension in 0..8 {
+                let mut strata = (0..16)
+                    .map(|index| Sobol.sample(key, index, dimension) >> 28)
+                    .collect::<Vec<_>>();
+                strata.sort();
+                assert_eq!(strata, (0..16).collect::<Vec<_>>());
+            }
+        }
+    }
+}
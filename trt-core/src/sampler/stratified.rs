@@ -0,0 +1,101 @@
+use super::{random_u32, Sampler};
+
+// Jitters every dimension within its own stratum out of `samples`, strata
+// being shuffled independently per dimension (Latin hypercube sampling)
+#[derive(Debug, Clone, Copy)]
+pub struct Stratified {
+    samples: u32,
+}
+
+impl Stratified {
+    pub fn new(samples: u32) -> Self {
+        Self { samples: samples.max(1) }
+    }
+}
+
+impl Sampler for Stratified {
+    fn sample(&self, key: u64, index: u32, dimension: u32) -> u32 {
+        // Samples past `samples` start over in freshly shuffled strata
+        let (round, index) = (index / self.samples, index % self.samples);
+        let shuffle = random_u32(&[key, dimension as u64, round as u64]);
+        let stratum = permute(index, self.samples, shuffle);
+
+        let jitter = random_u32(&[key, index as u64, dimension as u64, round as u64]) as f64 / (1u64 << 32) as f64;
+        let value = (stratum as f64 + jitter) / self.samples as f64;
+
+        (value * (1u64 << 32) as f64) as u32
+    }
+}
+
+// Random permutation of [0, length) indexed by `i`, from Kensler's
+// "Correlated Multi-Jittered Sampling" (2013)
+fn permute(mut i: u32, length: u32, p: u32) -> u32 {
+    let mut w = length - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+
+        if i < length {
+            return (i.wrapping_add(p)) % length
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strata(sampler: &Stratified, key: u64, indices: std::ops::Range<u32>, dimension: u32) -> Vec<u64> {
+        let mut strata = indices
+            .map(|index| sampler.sample(key, index, dimension) as u64 * sampler.samples as u64 >> 32)
+            .collect::<Vec<_>>();
+        strata.sort();
+        strata
+    }
+
+    #[test]
+    fn pixels_are_stratified() {
+        for &samples in &[1, 5, 16, 27] {
+            let sampler = Stratified::new(samples);
+            let expected = (0..samples as u64).collect::<Vec<_>>();
+
+            for key in 0..8 {
+                for dimension in 0..6 {
+                    assert_eq!(strata(&sampler, key, 0..samples, dimension), expected);
+                    assert_eq!(strata(&sampler, key, samples..2 * samples, dimension), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn permutations_are_bijective() {
+        for &length in &[1, 2, 7, 64, 100] {
+            let mut permuted = (0..length).map(|i| permute(i, length, 0x1234_5678)).collect::<Vec<_>>();
+            permuted.sort();
+            assert_eq!(permuted, (0..length).collect::<Vec<_>>());
+        }
+    }
+}
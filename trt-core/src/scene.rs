@@ -1,6 +1,15 @@
-use crate::{camera::Camera, prelude::{Hit, Vec3, Color}};
-use crate::utils::{compute_color, Rng};
+use crate::{accumulator::Accumulator, aov::{AovBuffers, PixelAovs, SurfaceSample}, adaptive::{AdaptiveRenderer, PixelEstimate}, camera::Camera, debug::DebugView, film::{Film, Filter}, framebuffer::Framebuffer, integrator::Integrator, prelude::{Hit, Ray, Vec3, Color}};
+use crate::spectrum::{sample_wavelength, spectrum_to_rgb};
+use crate::tile::{self, Tile, TileOrder};
+use crate::tonemap::ToneMap;
+use crate::environment::SharedEnvironment;
+use crate::sampler::{Independent, SampleStream, SharedSampler};
+use crate::utils::{hash, Rng};
 
+use std::ops::Range;
+use std::sync::Arc;
+
+#[derive(Clone)]
 pub struct Scene<World> {
     pub camera: Camera,
     pub width: usize,
@@ -9,22 +18,385 @@ pub struct Scene<World> {
     pub samples_per_px: u32,
     pub rays_per_sample: u32,
     pub ambiant_color: Vec3,
+    // Lights the world from afar in place of `ambiant_color`
+    pub environment: Option<SharedEnvironment>,
+    pub russian_roulette: Option<RussianRoulette>,
+    pub integrator: Integrator,
+    // Traces a single random wavelength per sample instead of RGB
+    pub spectral: bool,
+    pub tone_map: ToneMap,
+    pub adaptive: Option<AdaptiveSampling>,
+    // Every random decision of a sample derives from this seed, the pixel
+    // and the sample index, so renders are reproducible
+    pub seed: u64,
+    pub sampler: SharedSampler,
+    // Splats samples into the neighbouring pixels of rendered films
+    pub filter: Filter,
+    // Brightest a path traced sample may get from light having bounced off
+    // more than the surface seen by the camera, brighter ones being scaled down
+    pub clamp_indirect: Option<f32>,
+    pub outlier_rejection: Option<OutlierRejection>,
+    // Shades pixels by what the camera sees in place of any integrator
+    pub debug: Option<DebugView>,
+    // Only renders these pixels, leaving the rest of the image black
+    pub crop: Option<Tile>,
+}
+
+// Probabilistically ends paths based on their throughput once they are
+// `min_depth` bounces long, in place of the fixed `rays_per_sample` cap
+#[derive(Debug, Clone, Copy)]
+pub struct RussianRoulette {
+    pub min_depth: u32,
+    pub max_depth: u32,
+}
+
+// Samples pixels until their noise drops under `noise_threshold`, with
+// `samples_per_px` becoming the mean number of samples over the image
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveSampling {
+    // Samples taken by every pixel, and by each refinement pass
+    pub min_samples: u32,
+    pub max_samples: u32,
+    pub noise_threshold: f32,
+}
+
+// Scales samples brighter than `deviations` standard deviations above the
+// mean luminance of their pixel's samples so far down to that bound, once
+// the pixel has `min_samples`. Biased, but fireflies don't take thousands
+// of samples to average out anymore
+#[derive(Debug, Clone, Copy)]
+pub struct OutlierRejection {
+    pub deviations: f32,
+    pub min_samples: u32,
+}
+
+impl<World> Scene<World> {
+    // Path traced in the dark, with every optional feature off. Fields to
+    // change are then set with the struct update syntax
+    pub fn new(camera: Camera, width: usize, height: usize, world: World, samples_per_px: u32, rays_per_sample: u32) -> Self {
+        Self {
+            camera,
+            width,
+            height,
+            world,
+            samples_per_px,
+            rays_per_sample,
+            ambiant_color: Vec3::splat(0.),
+            environment: None,
+            russian_roulette: None,
+            integrator: Integrator::default(),
+            spectral: false,
+            tone_map: ToneMap::default(),
+            adaptive: None,
+            seed: 0,
+            sampler: Arc::new(Independent),
+            filter: Filter::default(),
+            clamp_indirect: None,
+            outlier_rejection: None,
+            debug: None,
+            crop: None,
+        }
+    }
+
+    pub fn max_depth(&self) -> usize {
+        match self.russian_roulette {
+            Some(RussianRoulette { max_depth, .. }) => max_depth as usize,
+            None => self.rays_per_sample as usize,
+        }
+    }
+
+    pub fn roulette_depth(&self) -> Option<usize> {
+        self.russian_roulette.map(|roulette| roulette.min_depth as usize)
+    }
+
+    // Radiance of rays escaping the world along `direction`
+    pub fn background(&self, direction: Vec3) -> Vec3 {
+        match &self.environment {
+            Some(environment) => environment.radiance(direction.unit()),
+            None => self.ambiant_color,
+        }
+    }
+
+    // Pixels to render, the whole image unless cropped
+    pub fn region(&self) -> Tile {
+        let image = Tile::new(0..self.width, 0..self.height);
+        match &self.crop {
+            Some(crop) => crop.intersect(&image),
+            None => image,
+        }
+    }
+
+    // `region()` split into tiles to render one after the other, or apart
+    pub fn tiles(&self, size: usize, order: TileOrder) -> Vec<Tile> {
+        tile::tiles(&self.region(), size, order)
+    }
+
+    // Total number of samples an adaptive render spreads over the region
+    pub fn sample_budget(&self) -> u64 {
+        let region = self.region();
+        self.samples_per_px as u64 * (region.width() * region.height()) as u64
+    }
 }
 
 impl<World: Hit> Scene<World> {
-    pub fn pixel_color(&self, (x, y): (usize, usize), mut rng: impl Rng) -> Color {
-        let summed_color = (0..self.samples_per_px)
-            .fold(Vec3::splat(0), |current_color, _r| {
-                let u = (x as f32 + rng.gen::<f32>()) / self.width as f32;
-                let v = (y as f32 + rng.gen::<f32>()) / self.height as f32;
+    // Linear RGB radiance arriving along `ray`
+    pub fn radiance(&self, mut ray: Ray, mut rng: impl Rng) -> Vec3 {
+        if let Some(view) = self.debug {
+            return view.shade(&ray, &self.world)
+        }
+
+        if !self.spectral {
+            return self.integrator.radiance(ray, self, rng)
+        }
+
+        let wavelength = sample_wavelength(&mut rng);
+        ray.wavelength = Some(wavelength);
+
+        let radiance = self.integrator.radiance(ray, self, &mut rng);
+        spectrum_to_rgb(radiance.x(), wavelength)
+    }
+
+    // Source of every random decision taken by the `sample`th sample of a pixel
+    pub fn sample_stream(&self, (x, y): (usize, usize), sample: u32) -> SampleStream<'_> {
+        SampleStream::new(&*self.sampler, hash(&[self.seed, x as u64, y as u64]), sample)
+    }
+
+    fn camera_ray(&self, (x, y): (usize, usize), stream: &mut SampleStream) -> ((f32, f32), Ray) {
+        let (jitter_u, jitter_v) = stream.get_2d();
+        let position = (x as f32 + jitter_u, y as f32 + jitter_v);
+        let u = position.0 / self.width as f32;
+        let v = position.1 / self.height as f32;
+
+        (position, self.camera.get_ray(u, v, stream))
+    }
+
+    // Takes the pixel's samples with indices in `samples`, handing each one's
+    // position on the image plane and radiance to `splat`. Outliers are
+    // rejected against the samples of `history` and the new ones
+    fn trace_pixel(&self, (x, y): (usize, usize), samples: Range<u32>, history: &PixelEstimate, mut splat: impl FnMut((f32, f32), Vec3)) -> PixelEstimate {
+        let mut running = *history;
+
+        samples.fold(PixelEstimate::default(), |mut estimate, sample| {
+            let mut stream = self.sample_stream((x, y), sample);
+            let (position, ray) = self.camera_ray((x, y), &mut stream);
+            let mut radiance = self.radiance(ray, &mut stream);
+
+            // Statistics keep the samples as traced, so that genuinely bright
+            // pixels don't stay rejected
+            if let Some(rejection) = &self.outlier_rejection {
+                let traced = radiance;
+                radiance = running.reject_outlier(radiance, rejection);
+                running.add(traced);
+            }
+
+            splat(position, radiance);
+            estimate.add(radiance);
+            estimate
+        })
+    }
+
+    // Takes the pixel's samples with indices in `samples`
+    pub fn sample_pixel(&self, pixel: (usize, usize), samples: Range<u32>) -> PixelEstimate {
+        self.trace_pixel(pixel, samples, &PixelEstimate::default(), |_, _| ())
+    }
+
+    // Same as `sample_pixel`, also splatting the samples into `film`, which
+    // may only cover a region around the pixel
+    pub fn splat_pixel(&self, film: &mut Film, pixel: (usize, usize), samples: Range<u32>) -> PixelEstimate {
+        self.trace_pixel(pixel, samples, &PixelEstimate::default(), |position, radiance| film.add_sample(position, radiance))
+    }
+
+    // Same as `splat_pixel`, taking `samples` more samples after those of
+    // the pixel's `history`
+    pub fn refine_pixel(&self, film: &mut Film, pixel: (usize, usize), history: &PixelEstimate, samples: u32) -> PixelEstimate {
+        let first = history.samples();
+        self.trace_pixel(pixel, first..first + samples, history, |position, radiance| film.add_sample(position, radiance))
+    }
+
+    // Mean linear RGB radiance over the pixel's own samples, as a box filter
+    // would. Adaptive sampling stops on this pixel's noise only, without any
+    // image wide budget
+    pub fn pixel_radiance(&self, pixel: (usize, usize)) -> Vec3 {
+        let adaptive = match self.adaptive {
+            Some(adaptive) => adaptive,
+            None => return self.sample_pixel(pixel, 0..self.samples_per_px).mean(),
+        };
+
+        let mut estimate = self.sample_pixel(pixel, 0..adaptive.min_samples);
+        while estimate.samples() < adaptive.max_samples && estimate.error() > adaptive.noise_threshold {
+            let first = estimate.samples();
+            let samples = adaptive.min_samples.max(1).min(adaptive.max_samples - first);
+            estimate.merge(&self.trace_pixel(pixel, first..first + samples, &estimate, |_, _| ()));
+        }
+
+        estimate.mean()
+    }
+
+    pub fn pixel_color(&self, pixel: (usize, usize)) -> Color {
+        Color::from_linear(self.tone_map.apply(self.pixel_radiance(pixel)))
+    }
+
+    // First hits of the camera rays of the pixel's samples with indices in
+    // `samples`, the same rays the radiance is traced from
+    pub fn pixel_aovs(&self, pixel: (usize, usize), samples: Range<u32>) -> PixelAovs {
+        samples.fold(PixelAovs::default(), |mut aovs, sample| {
+            let mut stream = self.sample_stream(pixel, sample);
+            let (_, ray) = self.camera_ray(pixel, &mut stream);
+
+            aovs.add(self.world.hit(&ray, 0.001, std::f32::MAX).map(|rec| SurfaceSample::new(&ray, &rec)));
+            aovs
+        })
+    }
+
+    // AOVs of the whole image, averaged over each pixel's first `samples` samples
+    pub fn render_aovs(&self, samples: u32) -> AovBuffers {
+        let pixels = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|pixel| self.pixel_aovs(pixel, 0..samples))
+            .collect();
+
+        AovBuffers::from_pixels(self.width, self.height, pixels)
+    }
+
+    pub fn film(&self) -> Film {
+        Film::new(self.width, self.height, self.filter)
+    }
+
+    pub fn accumulator(&self) -> Accumulator {
+        Accumulator::new(self.width, self.height, self.filter)
+    }
+
+    // Takes the pixel's next `samples` samples, following those already accumulated
+    pub fn accumulate_pixel(&self, accumulator: &mut Accumulator, pixel: (usize, usize), samples: u32) {
+        let history = *accumulator.get(pixel);
+        let estimate = self.refine_pixel(accumulator.film_mut(), pixel, &history, samples);
+        accumulator.add(pixel, &estimate);
+    }
+
+    pub fn accumulate_row(&self, accumulator: &mut Accumulator, y: usize, samples: u32) {
+        for x in 0..self.width {
+            self.accumulate_pixel(accumulator, (x, y), samples);
+        }
+    }
+
+    pub fn accumulate_tile(&self, accumulator: &mut Accumulator, tile: &Tile, samples: u32) {
+        for pixel in tile.pixels() {
+            self.accumulate_pixel(accumulator, pixel, samples);
+        }
+    }
+
+    // Adds `samples` more samples to every pixel of the region,
+    // `accumulator.framebuffer()` then holding the refined image
+    pub fn accumulate(&self, accumulator: &mut Accumulator, samples: u32) {
+        self.accumulate_tile(accumulator, &self.region(), samples);
+    }
+
+    pub fn render(&self) -> Framebuffer {
+        if let Some(adaptive) = self.adaptive {
+            let mut renderer = AdaptiveRenderer::new(self.width, self.height, self.filter, adaptive, self.sample_budget())
+                .cropped(self.region());
+
+            loop {
+                let pass = renderer.next_pass();
+                if pass.is_empty() {
+                    return renderer.framebuffer()
+                }
+
+                let mut film = self.film();
+                for (pixel, samples) in pass {
+                    let history = *renderer.accumulator().get(pixel);
+                    let estimate = self.refine_pixel(&mut film, pixel, &history, samples.len() as u32);
+                    renderer.record(pixel, &estimate);
+                }
+
+                renderer.merge_film(&film);
+            }
+        }
+
+        let mut film = self.film();
+
+        for pixel in self.region().pixels() {
+            self.splat_pixel(&mut film, pixel, 0..self.samples_per_px);
+        }
+
+        film.framebuffer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::film::FilterKind;
+    use crate::hit::{HitList, Sphere};
+    use crate::integrator;
+    use crate::material::Lambertian;
+    use crate::prelude::{Hit, MaterialBuilder};
+    use crate::sampler;
+    use crate::texture::Constant;
+
+    fn sphere(center: (f32, f32, f32), radius: f32, color: (f32, f32, f32)) -> Sphere<Lambertian<Constant>> {
+        Sphere::builder().center(center).radius(radius).material(Lambertian::colored(color))
+    }
+
+    // Under a sky
+    fn scene(sampler: &str) -> Scene<HitList<impl Hit>> {
+        let samples_per_px = 4;
+        let world = HitList::new(vec![
+            sphere((0., -100., 0.), 100., (0.5, 0.5, 0.5)),
+            sphere((-0.6, 0.5, 0.), 0.5, (0.8, 0.3, 0.3)),
+            sphere((0.6, 0.5, 0.4), 0.5, (0.3, 0.3, 0.8)),
+        ]);
+
+        Scene {
+            ambiant_color: Vec3::new(0.5, 0.7, 1.),
+            sampler: sampler::from_name(sampler, samples_per_px).unwrap(),
+            ..integrator::tests::scene_of(world, samples_per_px)
+        }
+    }
+
+    const TILINGS: [(usize, TileOrder); 4] = [(1, TileOrder::Scanline), (4, TileOrder::Spiral), (3, TileOrder::Hilbert), (16, TileOrder::Scanline)];
+
+    fn render_tiled(scene: &Scene<impl Hit>, size: usize, order: TileOrder) -> Vec<f32> {
+        let mut accumulator = scene.accumulator();
+        // Backwards, as another thread could have picked them up
+        for tile in scene.tiles(size, order).iter().rev() {
+            scene.accumulate_tile(&mut accumulator, tile, scene.samples_per_px);
+        }
+
+        accumulator.framebuffer().to_rgb_f32()
+    }
+
+    // Box filtered samples only reach their own pixel, whose sums then don't
+    // depend on the order tiles are rendered in
+    #[test]
+    fn same_box_filtered_image_for_any_tiling() {
+        for &name in &["independent", "stratified", "halton", "sobol"] {
+            let scene = scene(name);
+            let reference = scene.render().to_rgb_f32();
+
+            for &(size, order) in &TILINGS {
+                assert_eq!(render_tiled(&scene, size, order), reference, "{} sampler, {:?} tiles of {}", name, order, size);
+            }
+        }
+    }
 
-                let ray = self.camera.get_ray(u, v);
+    // Wider filters splat across tiles, summing samples in another order for
+    // each tiling, so images only agree up to rounding. A given tiling still
+    // renders the same image every time
+    #[test]
+    fn wide_filtered_tilings_agree_up_to_rounding() {
+        for &kind in &[FilterKind::Tent, FilterKind::Gaussian] {
+            let scene = Scene { filter: Filter { kind, radius: kind.default_radius() }, ..scene("independent") };
+            let reference = scene.render().to_rgb_f32();
 
-                current_color + compute_color(ray, &self.world, self.ambiant_color, self.rays_per_sample as _)
-            });
+            for &(size, order) in &TILINGS {
+                let image = render_tiled(&scene, size, order);
+                assert_eq!(render_tiled(&scene, size, order), image, "{:?} filter, {:?} tiles of {}", kind, order, size);
 
-        (summed_color / self.samples_per_px as f32)
-            .sqrt()
-            .into()
+                for (value, expected) in image.iter().zip(&reference) {
+                    assert!((value - expected).abs() <= 1e-5 * expected.abs().max(1.), "{:?} filter, {:?} tiles of {}", kind, order, size);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,87 @@
+use crate::prelude::Vec3;
+use crate::utils::Rng;
+
+// Wavelengths are in nanometers, sampled uniformly over the visible range
+pub const MIN_WAVELENGTH: f32 = 380.;
+pub const MAX_WAVELENGTH: f32 = 720.;
+
+const BINS: usize = 10;
+
+// Smits' basis spectra, "An RGB to Spectrum Conversion for Reflectances" (1999)
+const WHITE: [f32; BINS] = [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+const CYAN: [f32; BINS] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+const MAGENTA: [f32; BINS] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+const YELLOW: [f32; BINS] = [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+const RED: [f32; BINS] = [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+const GREEN: [f32; BINS] = [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+const BLUE: [f32; BINS] = [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+
+// Scales each channel so that a constant spectrum of 1 integrates to white
+const WHITE_BALANCE: (f32, f32, f32) = (0.007_790_544, 0.009_848_522, 0.010_303_869);
+
+pub fn sample_wavelength(mut rng: impl Rng) -> f32 {
+    rng.gen_range(MIN_WAVELENGTH, MAX_WAVELENGTH)
+}
+
+// Value at `wavelength` of a smooth spectrum with the given RGB color
+pub fn rgb_to_spectrum(rgb: Vec3, wavelength: f32) -> f32 {
+    let bin = ((wavelength - MIN_WAVELENGTH) / (MAX_WAVELENGTH - MIN_WAVELENGTH) * BINS as f32) as usize;
+    let bin = bin.min(BINS - 1);
+    let (r, g, b) = (rgb.x(), rgb.y(), rgb.z());
+
+    if r <= g && r <= b {
+        r * WHITE[bin] + if g <= b {
+            (g - r) * CYAN[bin] + (b - g) * BLUE[bin]
+        } else {
+            (b - r) * CYAN[bin] + (g - b) * GREEN[bin]
+        }
+    } else if g <= r && g <= b {
+        g * WHITE[bin] + if r <= b {
+            (r - g) * MAGENTA[bin] + (b - r) * BLUE[bin]
+        } else {
+            (b - g) * MAGENTA[bin] + (r - b) * RED[bin]
+        }
+    } else {
+        b * WHITE[bin] + if r <= g {
+            (r - b) * YELLOW[bin] + (g - r) * GREEN[bin]
+        } else {
+            (g - b) * YELLOW[bin] + (r - g) * RED[bin]
+        }
+    }
+}
+
+// Linear RGB estimate of a spectrum known only at a uniformly sampled `wavelength`
+pub fn spectrum_to_rgb(value: f32, wavelength: f32) -> Vec3 {
+    let (x, y, z) = cie_xyz(wavelength);
+
+    let r = 3.2406 * x - 1.5372 * y - 0.4986 * z;
+    let g = -0.9689 * x + 1.8758 * y + 0.0415 * z;
+    let b = 0.0557 * x - 0.2040 * y + 1.0570 * z;
+
+    let (r_scale, g_scale, b_scale) = WHITE_BALANCE;
+    value * (MAX_WAVELENGTH - MIN_WAVELENGTH) * Vec3::new(r * r_scale, g * g_scale, b * b_scale)
+}
+
+// Gray color holding the spectral value of `rgb` when rendering a single wavelength
+pub fn at_wavelength(rgb: Vec3, wavelength: Option<f32>) -> Vec3 {
+    match wavelength {
+        Some(wavelength) => Vec3::splat(rgb_to_spectrum(rgb, wavelength)),
+        None => rgb,
+    }
+}
+
+// Multi-lobe fit of the CIE 1931 color matching functions,
+// Wyman et al. "Simple Analytic Approximations to the CIE XYZ Color Matching Functions" (2013)
+fn cie_xyz(wavelength: f32) -> (f32, f32, f32) {
+    let lobe = |mean: f32, below: f32, above: f32| {
+        let deviation = if wavelength < mean { below } else { above };
+        let t = (wavelength - mean) / deviation;
+        (-0.5 * t * t).exp()
+    };
+
+    let x = 1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7) - 0.065 * lobe(501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8);
+
+    (x, y, z)
+}
@@ -1,5 +1,6 @@
 use crate::prelude::{Texture, Vec3};
 use crate::perlin::Perlin;
+use crate::utils::Rng;
 
 pub struct Noise {
     perlin: Perlin,
@@ -7,9 +8,9 @@ pub struct Noise {
 }
 
 impl Noise {
-    pub fn from_scale(scale: f32) -> Self {
+    pub fn from_scale(scale: f32, rng: impl Rng) -> Self {
         Self {
-            perlin: Perlin::new(),
+            perlin: Perlin::new(rng),
             scale,
         }
     }
@@ -0,0 +1,233 @@
+use std::ops::Range;
+
+// Rectangle of pixels, rows counted from the bottom one like framebuffers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tile {
+    pub xs: Range<usize>,
+    pub ys: Range<usize>,
+}
+
+impl Tile {
+    pub fn new(xs: Range<usize>, ys: Range<usize>) -> Self {
+        Self { xs, ys }
+    }
+
+    pub fn width(&self) -> usize {
+        self.xs.len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.ys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.xs.is_empty() || self.ys.is_empty()
+    }
+
+    pub fn contains(&self, (x, y): (usize, usize)) -> bool {
+        self.xs.contains(&x) && self.ys.contains(&y)
+    }
+
+    // Pixels shared with `other`, possibly none
+    pub fn intersect(&self, other: &Tile) -> Tile {
+        let xs = self.xs.start.max(other.xs.start)..self.xs.end.min(other.xs.end);
+        let ys = self.ys.start.max(other.ys.start)..self.ys.end.min(other.ys.end);
+
+        Tile::new(xs.start..xs.end.max(xs.start), ys.start..ys.end.max(ys.start))
+    }
+
+    // Row after row, starting from the bottom one
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize)> {
+        let xs = self.xs.clone();
+        self.ys.clone().flat_map(move |y| xs.clone().map(move |x| (x, y)))
+    }
+}
+
+// Order tiles are rendered in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileOrder {
+    // Row after row, starting from the bottom one
+    Scanline,
+    // Outwards from the center, which usually matters most
+    Spiral,
+    // Along a Hilbert curve, each tile following a neighbouring one
+    Hilbert,
+}
+
+impl TileOrder {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "scanline" => Some(TileOrder::Scanline),
+            "spiral" => Some(TileOrder::Spiral),
+            "hilbert" => Some(TileOrder::Hilbert),
+            _ => None,
+        }
+    }
+}
+
+impl Default for TileOrder {
+    fn default() -> Self {
+        TileOrder::Scanline
+    }
+}
+
+// Splits `region` into `size` pixels wide squares, the last ones of each row
+// and column being cut short, in `order`
+pub fn tiles(region: &Tile, size: usize, order: TileOrder) -> Vec<Tile> {
+    let size = size.max(1);
+    let columns = (region.width() + size - 1) / size;
+    let rows = (region.height() + size - 1) / size;
+
+    let cells = match order {
+        TileOrder::Scanline => (0..rows).flat_map(|row| (0..columns).map(move |column| (column, row))).collect(),
+        TileOrder::Spiral => spiral(columns, rows),
+        TileOrder::Hilbert => {
+            let mut cells = (0..rows).flat_map(|row| (0..columns).map(move |column| (column, row))).collect::<Vec<_>>();
+            let side = columns.max(rows).next_power_of_two();
+            cells.sort_by_key(|&cell| hilbert_index(side, cell));
+            cells
+        },
+    };
+
+    cells
+        .into_iter()
+        .map(|(column, row)| {
+            let x = region.xs.start + column * size;
+            let y = region.ys.start + row * size;
+            Tile::new(x..(x + size).min(region.xs.end), y..(y + size).min(region.ys.end))
+        })
+        .collect()
+}
+
+// Cells of a grid, walking a square spiral out of its center
+fn spiral(columns: usize, rows: usize) -> Vec<(usize, usize)> {
+    const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+    let inside = |x: isize, y: isize| x >= 0 && y >= 0 && x < columns as isize && y < rows as isize;
+    let (mut x, mut y) = ((columns as isize - 1) / 2, (rows as isize - 1) / 2);
+
+    let mut cells = Vec::with_capacity(columns * rows);
+    if inside(x, y) {
+        cells.push((x as usize, y as usize));
+    }
+
+    // Legs of the spiral grow by one every two turns
+    let mut length = 1;
+    let mut direction = 0;
+    while cells.len() < columns * rows {
+        for _ in 0..2 {
+            let (dx, dy) = DIRECTIONS[direction % 4];
+            for _ in 0..length {
+                x += dx;
+                y += dy;
+                if inside(x, y) {
+                    cells.push((x as usize, y as usize));
+                }
+            }
+            direction += 1;
+        }
+        length += 1;
+    }
+
+    cells
+}
+
+// Distance along the Hilbert curve filling a `side` cells wide square,
+// `side` being a power of two
+fn hilbert_index(side: usize, (mut x, mut y): (usize, usize)) -> usize {
+    let mut index = 0;
+    let mut s = side / 2;
+
+    while s > 0 {
+        let rx = (x & s > 0) as usize;
+        let ry = (y & s > 0) as usize;
+        index += s * s * ((3 * rx) ^ ry);
+
+        // Rotates the quadrant so that its curve starts where the last one ended
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+
+        s /= 2;
+    }
+
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDERS: [TileOrder; 3] = [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert];
+
+    // Panics unless the tiles of each order cover `region` of a `width` by
+    // `height` image, each pixel exactly once
+    fn assert_covers(region: &Tile, width: usize, height: usize, size: usize) {
+        for &order in &ORDERS {
+            let mut covered = vec![0; width * height];
+
+            for tile in tiles(region, size, order) {
+                assert!(!tile.is_empty(), "{:?} tiles of {} in {:?}: empty {:?}", order, size, region, tile);
+                for (x, y) in tile.pixels() {
+                    assert!(region.contains((x, y)), "{:?} tiles of {} in {:?}: {:?} outside", order, size, region, (x, y));
+                    covered[x + y * width] += 1;
+                }
+            }
+
+            for (x, y) in Tile::new(0..width, 0..height).pixels() {
+                let expected = if region.contains((x, y)) { 1 } else { 0 };
+                assert_eq!(covered[x + y * width], expected, "{:?} tiles of {} in {:?} at {:?}", order, size, region, (x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn cover_whole_images() {
+        for &(width, height) in &[(37, 23), (23, 37), (64, 64), (1, 50), (50, 1), (7, 7)] {
+            for &size in &[1, 3, 4, 8, 16, 100] {
+                assert_covers(&Tile::new(0..width, 0..height), width, height, size);
+            }
+        }
+    }
+
+    #[test]
+    fn cover_crops_at_the_image_edges() {
+        let (width, height) = (37, 23);
+        let image = Tile::new(0..width, 0..height);
+        let crops = [
+            Tile::new(30..50, 10..40),
+            Tile::new(0..5, 0..23),
+            Tile::new(12..37, 0..9),
+            Tile::new(5..29, 3..20),
+        ];
+
+        for crop in &crops {
+            for &size in &[1, 3, 4, 16] {
+                assert_covers(&crop.intersect(&image), width, height, size);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_regions_have_no_tiles() {
+        let image = Tile::new(0..37, 0..23);
+        let outside = Tile::new(40..50, 5..10).intersect(&image);
+
+        for &order in &ORDERS {
+            assert!(tiles(&outside, 4, order).is_empty());
+        }
+    }
+
+    #[test]
+    fn spirals_start_from_the_center() {
+        let region = Tile::new(0..37, 0..23);
+        let first = &tiles(&region, 4, TileOrder::Spiral)[0];
+
+        // 10 by 6 tiles
+        assert_eq!(first, &Tile::new(16..20, 8..12));
+    }
+}
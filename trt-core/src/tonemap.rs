@@ -0,0 +1,93 @@
+use crate::{color::luminance, prelude::Vec3};
+
+// Operators compressing linear radiance into the displayable [0, 1] range
+#[derive(Debug, Clone, Copy)]
+pub enum ToneMapper {
+    // Clips every channel above 1
+    Clamp,
+    Reinhard,
+    // Reinhard reaching white at the `white` luminance instead of infinity
+    ExtendedReinhard { white: f32 },
+    // Narkowicz's fit of the ACES filmic curve
+    Aces,
+    // John Hable's Uncharted 2 filmic curve
+    Hable,
+}
+
+impl ToneMapper {
+    pub fn from_name(name: &str, white: f32) -> Option<Self> {
+        match name {
+            "clamp" => Some(ToneMapper::Clamp),
+            "reinhard" => Some(ToneMapper::Reinhard),
+            "extended_reinhard" => Some(ToneMapper::ExtendedReinhard { white }),
+            "aces" => Some(ToneMapper::Aces),
+            "hable" => Some(ToneMapper::Hable),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ToneMap {
+    pub mapper: ToneMapper,
+    // Exposure compensation in stops, applied before the operator
+    pub exposure: f32,
+}
+
+impl Default for ToneMap {
+    fn default() -> Self {
+        Self { mapper: ToneMapper::Clamp, exposure: 0. }
+    }
+}
+
+impl ToneMap {
+    // Maps linear radiance to linear [0, 1] color, still to be gamma corrected
+    pub fn apply(&self, radiance: Vec3) -> Vec3 {
+        let radiance = radiance.max(Vec3::splat(0.)) * self.exposure.exp2();
+
+        let mapped = match self.mapper {
+            ToneMapper::Clamp => radiance,
+            ToneMapper::Reinhard => scale_luminance(radiance, |l| l / (1. + l)),
+            ToneMapper::ExtendedReinhard { white } => {
+                scale_luminance(radiance, |l| l * (1. + l / (white * white)) / (1. + l))
+            },
+            ToneMapper::Aces => {
+                let numerator = radiance * (radiance * 2.51 + Vec3::splat(0.03));
+                let denominator = radiance * (radiance * 2.43 + Vec3::splat(0.59)) + Vec3::splat(0.14);
+                numerator / denominator
+            },
+            ToneMapper::Hable => {
+                const EXPOSURE_BIAS: f32 = 2.;
+                const WHITE: f32 = 11.2;
+
+                hable(radiance * EXPOSURE_BIAS) / hable(Vec3::splat(WHITE))
+            },
+        };
+
+        mapped.min(Vec3::splat(1.))
+    }
+}
+
+// Maps the luminance only, keeping the hue and saturation
+fn scale_luminance(color: Vec3, map: impl Fn(f32) -> f32) -> Vec3 {
+    let luminance = luminance(color);
+
+    if luminance > 0. {
+        color * (map(luminance) / luminance)
+    } else {
+        color
+    }
+}
+
+fn hable(x: Vec3) -> Vec3 {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+
+    let numerator = x * (x * A + Vec3::splat(C * B)) + Vec3::splat(D * E);
+    let denominator = x * (x * A + Vec3::splat(B)) + Vec3::splat(D * F);
+    numerator / denominator - Vec3::splat(E / F)
+}
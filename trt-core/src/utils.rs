@@ -1,38 +1,70 @@
-use crate::prelude::{Hit, Ray, Vec3};
-
-pub use rand::{Rng, thread_rng, seq::SliceRandom, distributions::Distribution};
-
-pub fn compute_color(mut ray: Ray, world: &impl Hit, ambiant_color: Vec3, max_depth: usize) -> Vec3 {
-    let mut components = Vec::with_capacity(max_depth);
-
-    for _depth in 0..max_depth {
-        if let Some(rec) = world.hit(&ray, 0.001, std::f32::MAX) {
-            let emitted = rec.mat.emitted(rec.u, rec.v, rec.p);
-
-            if let Some((scattered, attenuation)) = rec.mat.scatter(&ray, &rec) {
-                components.push((emitted, attenuation));
-                ray = scattered;
-            } else {
-                return components.into_iter().rev()
-                    .fold(emitted, |r, (e, a)| r * a + e)
-            }
-        } else {
-            break
-        }
-    }
+use crate::prelude::Vec3;
+
+pub use rand::{Rng, RngCore, SeedableRng, seq::SliceRandom};
+// Same numbers on every target, unlike `SmallRng` which is another generator
+// on 32 bits targets such as wasm
+pub use rand_pcg::Pcg32;
+
+use crate::prelude::Ray;
+
+use std::f32::consts::PI;
+
+// Whether two hits along the same ray are the same point
+pub fn same_hit(t: f32, other_t: f32) -> bool {
+    (t - other_t).abs() <= 1e-3 * t.abs().max(1e-3)
+}
+
+// Mixes `values` into a well distributed seed, with splitmix64's finalizer
+pub fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x9e37_79b9_7f4a_7c15, |hash: u64, &value| {
+        let mut z = (hash ^ value).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    })
+}
+
+// Random numbers for decisions taken while intersecting, where no sampler is
+// at hand. Follows the sampler through the seed of the ray's path, the ray
+// itself telling apart the decisions along it
+pub fn ray_rng(ray: &Ray, salt: f32) -> Pcg32 {
+    let bits = |a: f32, b: f32| (a.to_bits() as u64) << 32 | b.to_bits() as u64;
+    let (origin, direction) = (ray.origin, ray.direction);
+
+    Pcg32::seed_from_u64(hash(&[
+        ray.seed,
+        bits(origin.x(), origin.y()),
+        bits(origin.z(), direction.x()),
+        bits(direction.y(), direction.z()),
+        bits(ray.time, salt),
+    ]))
+}
 
-    components.into_iter().rev()
-        .fold(ambiant_color, |r, (e, a)| r * a + e)
+pub fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let (pdf2, other_pdf2) = (pdf * pdf, other_pdf * other_pdf);
+    if pdf2 + other_pdf2 > 0. { pdf2 / (pdf2 + other_pdf2) } else { 0. }
 }
 
+// Index below `count` from a single random number, `gen_range` rejecting
+// and drawing again now and then
+pub fn pick(mut rng: impl Rng, count: usize) -> usize {
+    ((rng.gen::<f32>() * count as f32) as usize).min(count.max(1) - 1)
+}
+
+// Uniform over the unit sphere's surface, from two random numbers
 pub fn random_in_unit_sphere(mut rng: impl Rng) -> Vec3 {
-    let [x, y, z]: [f32; 3] = rand_distr::UnitSphere.sample(&mut rng);
-    Vec3::new(x, y, z)
+    let z = 1. - 2. * rng.gen::<f32>();
+    let phi = 2. * PI * rng.gen::<f32>();
+    let r = (1. - z * z).max(0.).sqrt();
+
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
 }
 
 pub fn random_in_unit_disk(mut rng: impl Rng) -> Vec3 {
-    let [x, y]: [f32; 2] = rand_distr::UnitDisc.sample(&mut rng);
-    Vec3::new(x, y, 0)
+    let r = rng.gen::<f32>().sqrt();
+    let theta = 2. * PI * rng.gen::<f32>();
+
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0)
 }
 
 pub fn reflect(v: Vec3, n: Vec3) -> Vec3 {
@@ -59,7 +91,6 @@ pub fn schlick(cosine: f32, ref_idx: f32) -> f32 {
 }
 
 pub fn sphere_uv(p: Vec3) -> (f32, f32) {
-    use std::f32::consts::PI;
     let phi = f32::atan2(p.z(), p.x());
     let theta = p.y().asin();
     let u = 1. - (phi + PI) / (2. * PI);
@@ -68,9 +99,82 @@ pub fn sphere_uv(p: Vec3) -> (f32, f32) {
 }
 
 pub fn cylinder_uv(p: Vec3) -> (f32, f32) {
-    use std::f32::consts::PI;
     let phi = f32::atan2(p.z(), p.x());
     let u = 1. - (phi + PI) / (2. * PI);
     let v = p.y() % 1.;
     (u, v)
 }
+
+pub struct Onb {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl Onb {
+    pub fn from_w(n: Vec3) -> Self {
+        let w = n.unit();
+        let a = if w.x().abs() > 0.9 { Vec3::new(0, 1, 0) } else { Vec3::new(1, 0, 0) };
+        let v = Vec3::cross(w, a).unit();
+        let u = Vec3::cross(w, v);
+
+        Self { u, v, w }
+    }
+
+    pub fn local(&self, a: Vec3) -> Vec3 {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+}
+
+// Uniformly samples the cone of directions from `origin` that hit the sphere
+pub fn sample_sphere_solid_angle(center: Vec3, radius: f32, origin: Vec3, mut rng: impl Rng) -> Vec3 {
+    let direction = center - origin;
+    let distance_squared = direction.squared_len();
+
+    if distance_squared <= radius * radius {
+        return random_in_unit_sphere(rng)
+    }
+
+    let cos_theta_max = (1. - radius * radius / distance_squared).sqrt();
+    let z = 1. + rng.gen::<f32>() * (cos_theta_max - 1.);
+    let phi = 2. * PI * rng.gen::<f32>();
+    let sin_theta = (1. - z * z).max(0.).sqrt();
+
+    Onb::from_w(direction).local(Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z))
+}
+
+pub fn sphere_solid_angle_pdf(center: Vec3, radius: f32, origin: Vec3, direction: Vec3) -> f32 {
+    let oc = origin - center;
+    let distance_squared = oc.squared_len();
+    let radius_squared = radius * radius;
+
+    if distance_squared <= radius_squared {
+        return 1. / (4. * PI)
+    }
+
+    let b = Vec3::dot(oc, direction);
+    let discriminant = b * b - Vec3::dot(direction, direction) * (distance_squared - radius_squared);
+    if b >= 0. || discriminant <= 0. {
+        return 0.
+    }
+
+    let cos_theta_max = (1. - radius_squared / distance_squared).sqrt();
+    1. / (2. * PI * (1. - cos_theta_max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Scenes built from a seed must come out the same in every build
+    #[test]
+    fn seeded_numbers_are_pinned() {
+        let mut rng = Pcg32::seed_from_u64(0);
+        let values = (0..4).map(|_| rng.next_u32()).collect::<Vec<_>>();
+        assert_eq!(values, [298703107, 4236525527, 336081875, 1056616254]);
+
+        let ray = Ray { origin: Vec3::new(1., 2., 3.), direction: Vec3::new(0., 0., -1.), time: 0.5, wavelength: None, seed: 42 };
+        assert_eq!(ray_rng(&ray, 1.).next_u32(), 2311959780);
+        assert_eq!(hash(&[1, 2, 3]), 5246500226706903259);
+    }
+}
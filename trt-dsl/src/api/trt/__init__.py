@@ -2,6 +2,7 @@ import _trt
 
 from . import shape
 from . import material
+from . import phase
 
 def render(world, **config):
     DEFAULT_WIDTH = 500
@@ -9,6 +10,25 @@ def render(world, **config):
     DEFAULT_SPX = 50
     DEFAULT_RPS = 25
     DEFAULT_AMBIANT = (0, 0, 0)
+    DEFAULT_SKY = None
+    DEFAULT_RUSSIAN_ROULETTE = None
+    DEFAULT_INTEGRATOR = 'path'
+    DEFAULT_PHOTONS = 200000
+    DEFAULT_PHOTON_RADIUS = 5
+    DEFAULT_AO_RADIUS = None
+    DEFAULT_SPECTRAL = False
+    DEFAULT_TONE_MAP = 'clamp'
+    DEFAULT_EXPOSURE = 0
+    DEFAULT_WHITE_POINT = 4
+    DEFAULT_ADAPTIVE = None
+    DEFAULT_SEED = 0
+    DEFAULT_SAMPLER = 'independent'
+    DEFAULT_FILTER = 'box'
+    DEFAULT_FILTER_RADIUS = None
+    DEFAULT_CLAMP_INDIRECT = None
+    DEFAULT_OUTLIER_REJECTION = None
+    DEFAULT_DEBUG = None
+    DEFAULT_CROP = None
     DEFAULT_CAMERA = {
         'look_at': (0, 0, 0),
         'look_from': (0, 0, 0)
@@ -21,6 +41,25 @@ def render(world, **config):
         'samples_per_px': config.get('samples_per_px', DEFAULT_SPX),
         'rays_per_sample': config.get('rays_per_sample', DEFAULT_RPS),
         'ambiant_color': config.get('ambiant_color', DEFAULT_AMBIANT),
+        'sky': _sky(config.get('ambiant', {}).get('sky', DEFAULT_SKY)),
+        'russian_roulette': _russian_roulette(config.get('russian_roulette', DEFAULT_RUSSIAN_ROULETTE)),
+        'integrator': config.get('integrator', DEFAULT_INTEGRATOR),
+        'photons': config.get('photons', DEFAULT_PHOTONS),
+        'photon_radius': config.get('photon_radius', DEFAULT_PHOTON_RADIUS),
+        'ao_radius': config.get('ao_radius', DEFAULT_AO_RADIUS),
+        'spectral': config.get('spectral', DEFAULT_SPECTRAL),
+        'tone_map': config.get('tone_map', DEFAULT_TONE_MAP),
+        'exposure': config.get('exposure', DEFAULT_EXPOSURE),
+        'white_point': config.get('white_point', DEFAULT_WHITE_POINT),
+        'adaptive': _adaptive(config.get('adaptive', DEFAULT_ADAPTIVE)),
+        'seed': config.get('seed', DEFAULT_SEED),
+        'sampler': config.get('sampler', DEFAULT_SAMPLER),
+        'filter': config.get('filter', DEFAULT_FILTER),
+        'filter_radius': config.get('filter_radius', DEFAULT_FILTER_RADIUS),
+        'clamp_indirect': config.get('clamp_indirect', DEFAULT_CLAMP_INDIRECT),
+        'outlier_rejection': _outlier_rejection(config.get('outlier_rejection', DEFAULT_OUTLIER_REJECTION)),
+        'debug': config.get('debug', DEFAULT_DEBUG),
+        'crop': config.get('crop', DEFAULT_CROP),
         'camera': _camera(**config.get('camera', DEFAULT_CAMERA))
     }
 
@@ -28,3 +67,23 @@ def render(world, **config):
 
 def _camera(look_from, look_at):
     return _trt.Camera(look_from, look_at)
+
+def _russian_roulette(config):
+    if config is None:
+        return None
+    return (config.get('min_depth', 3), config.get('max_depth', 100))
+
+def _sky(config):
+    if config is None:
+        return None
+    return (config.get('sun_elevation', 45), config.get('sun_azimuth', 180), config.get('turbidity', 3), config.get('intensity', 1))
+
+def _adaptive(config):
+    if config is None:
+        return None
+    return (config.get('min_samples', 16), config.get('max_samples', 1000), config.get('noise_threshold', 0.01))
+
+def _outlier_rejection(config):
+    if config is None:
+        return None
+    return (config.get('deviations', 3), config.get('min_samples', 16))
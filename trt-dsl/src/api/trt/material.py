@@ -9,6 +9,13 @@ def metallic(color, fuzz=0):
 def dielectric(ref_idx):
     return _trt.Material.dielectric(ref_idx)
 
+# Dispersive dielectrics, wavelengths in micrometers, only split light in spectral renders
+def dielectric_cauchy(a, b):
+    return _trt.Material.dielectric_cauchy(a, b)
+
+def dielectric_sellmeier(b, c):
+    return _trt.Material.dielectric_sellmeier(b, c)
+
 def diffuse_color(color):
     return _trt.Material.diffuse_color(color)
 
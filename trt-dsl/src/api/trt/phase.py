@@ -0,0 +1,17 @@
+import _trt
+
+# Phase functions of participating media, passed to `constant_medium`,
+# `turbulent_medium` and `voxel_medium`
+def isotropic():
+    return _trt.Phase.isotropic()
+
+# g from -1 for backward to 1 for forward scattering
+def henyey_greenstein(g):
+    return _trt.Phase.henyey_greenstein(float(g))
+
+def rayleigh():
+    return _trt.Phase.rayleigh()
+
+# A weight share of forward scattering, the rest backward
+def double_henyey_greenstein(forward, backward, weight):
+    return _trt.Phase.double_henyey_greenstein(float(forward), float(backward), float(weight))
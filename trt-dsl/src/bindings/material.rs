@@ -2,7 +2,7 @@ use crate::{future::PyFuture, prelude::*};
 use super::{shape::SharedHit, vec3::PyVec3};
 
 use trt_core::{
-    material::{Dielectric, Diffuse, Lambertian, Metal},
+    material::{Dielectric, Dispersion, Diffuse, Lambertian, Metal},
     prelude::*,
     texture::Image,
 };
@@ -54,6 +54,20 @@ impl PyMaterial {
         Self::new(Dielectric::new(ref_idx))
     }
 
+    #[pyclassmethod]
+    fn dielectric_cauchy(_cls: PyClassRef, a: f32, b: f32) -> Self {
+        Self::new(Dielectric::dispersive(Dispersion::Cauchy { a, b }))
+    }
+
+    #[pyclassmethod]
+    fn dielectric_sellmeier(_cls: PyClassRef, b: PyVec3, c: PyVec3) -> Self {
+        let (b, c) = (b.into_vec(), c.into_vec());
+        Self::new(Dielectric::dispersive(Dispersion::Sellmeier {
+            b: [b.x(), b.y(), b.z()],
+            c: [c.x(), c.y(), c.z()],
+        }))
+    }
+
     #[pyclassmethod]
     fn diffuse_color(_cls: PyClassRef, color: PyVec3) -> Self {
         Self::new(Diffuse::colored(color.into_vec()))
@@ -28,6 +28,7 @@ mod vec3;
 mod float;
 mod scene;
 mod material;
+mod phase;
 mod shape;
 
 pub use scene::{DynScene, DynSceneResult};
@@ -88,6 +89,7 @@ impl<'vm> SceneInjector<'vm> {
 fn make_trt_module(vm: &VirtualMachine) -> PyObjectRef {
     rpy::py_module!(vm, TRT_INTERNAL_MODULE_NAME, {
         "Material" => material::PyMaterial::make_class(&vm.ctx),
+        "Phase" => phase::PyPhase::make_class(&vm.ctx),
         "Shape" => shape::PyShape::make_class(&vm.ctx),
         "Scene" => scene::PyScene::make_class(&vm.ctx),
         "Camera" => camera::PyCamera::make_class(&vm.ctx),
@@ -0,0 +1,39 @@
+use crate::prelude::*;
+
+use trt_core::material::PhaseFunction;
+
+trt_py_class! { "Phase", PyPhase,
+    #[derive(Clone, Copy)]
+    pub struct PyPhase(pub(crate) PhaseFunction);
+}
+
+impl TryFromObject for PyPhase {
+    fn try_from_object(vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<Self> {
+        let phase: PyRef<Self> = obj.try_into_ref(vm)?;
+
+        Ok(*phase)
+    }
+}
+
+#[rpy::pyimpl]
+impl PyPhase {
+    #[pyclassmethod]
+    fn isotropic(_cls: PyClassRef) -> Self {
+        Self(PhaseFunction::Isotropic)
+    }
+
+    #[pyclassmethod]
+    fn henyey_greenstein(_cls: PyClassRef, g: f32) -> Self {
+        Self(PhaseFunction::HenyeyGreenstein(g))
+    }
+
+    #[pyclassmethod]
+    fn rayleigh(_cls: PyClassRef) -> Self {
+        Self(PhaseFunction::Rayleigh)
+    }
+
+    #[pyclassmethod]
+    fn double_henyey_greenstein(_cls: PyClassRef, forward: f32, backward: f32, weight: f32) -> Self {
+        Self(PhaseFunction::DoubleHenyeyGreenstein { forward, backward, weight })
+    }
+}
@@ -1,9 +1,13 @@
 use crate::{future::PyFuture, prelude::*};
 use super::{camera::PyCamera, material::MaterialError, shape::PyShape, vec3::PyVec3};
 
-use trt_core::{hit::HitList, prelude::*, scene::Scene};
+use trt_core::{debug::DebugView, environment::{SharedEnvironment, Sky}, film::{Filter, FilterKind}, hit::HitList, integrator::Integrator, prelude::*, sampler, scene::{Scene, RussianRoulette, AdaptiveSampling, OutlierRejection}, tile::Tile, tonemap::{ToneMap, ToneMapper}};
+use rpy::obj::objstr::PyStringRef;
 
 use futures::prelude::*;
+use std::sync::Arc;
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
 
 pub type DynScene = Scene<HitList<Rc<dyn Hit>>>;
 pub type DynSceneResult = Result<Rc<DynScene>, Rc<MaterialError>>;
@@ -21,6 +25,26 @@ struct PySceneArgs {
     samples_per_px: u32,
     rays_per_sample: u32,
     ambiant_color: PyVec3,
+    sky: Option<(f32, f32, f32, f32)>,
+    russian_roulette: Option<(u32, u32)>,
+    integrator: PyStringRef,
+    photons: usize,
+    photon_radius: f32,
+    ao_radius: Option<f32>,
+    spectral: bool,
+    tone_map: PyStringRef,
+    exposure: f32,
+    white_point: f32,
+    adaptive: Option<(u32, u32, f32)>,
+    seed: u64,
+    sampler: PyStringRef,
+    filter: PyStringRef,
+    filter_radius: Option<f32>,
+    clamp_indirect: Option<f32>,
+    outlier_rejection: Option<(f32, u32)>,
+    debug: Option<PyStringRef>,
+    // x, y, width and height from the image's top left corner
+    crop: Option<(usize, usize, usize, usize)>,
 }
 
 #[rpy::pyimpl]
@@ -50,17 +74,68 @@ impl PyScene {
         let samples_per_px = args.samples_per_px;
         let rays_per_sample = args.rays_per_sample;
         let ambiant_color = args.ambiant_color.into_vec();
+        let environment = args.sky
+            .map(|(elevation, azimuth, turbidity, intensity)| -> SharedEnvironment { Arc::new(Sky::new(elevation, azimuth, turbidity, intensity)) });
+        let russian_roulette = args.russian_roulette
+            .map(|(min_depth, max_depth)| RussianRoulette { min_depth, max_depth });
+        // Photon maps can only be built once the world is loaded
+        let integrator = match args.integrator.as_str() {
+            "path" => Some(Integrator::PathTracing),
+            "bidirectional" => Some(Integrator::Bidirectional),
+            "ao" => Some(Integrator::AmbientOcclusion { radius: args.ao_radius.unwrap_or(std::f32::MAX) }),
+            "whitted" => Some(Integrator::Whitted),
+            "photon" => None,
+            other => return Err(vm.new_value_error(format!("Unknown integrator '{}'", other))),
+        };
+        let photon_mapping = integrator.is_none();
+        let (photons, photon_radius) = (args.photons, args.photon_radius);
+        let spectral = args.spectral;
+        let seed = args.seed;
+        let sampler = sampler::from_name(args.sampler.as_str(), samples_per_px)
+            .ok_or_else(|| vm.new_value_error(format!("Unknown sampler '{}'", args.sampler.as_str())))?;
+        let kind = FilterKind::from_name(args.filter.as_str())
+            .ok_or_else(|| vm.new_value_error(format!("Unknown filter '{}'", args.filter.as_str())))?;
+        let filter = Filter { kind, radius: args.filter_radius.unwrap_or_else(|| kind.default_radius()) };
+        let mapper = ToneMapper::from_name(args.tone_map.as_str(), args.white_point)
+            .ok_or_else(|| vm.new_value_error(format!("Unknown tone mapper '{}'", args.tone_map.as_str())))?;
+        let tone_map = ToneMap { mapper, exposure: args.exposure };
+        let adaptive = args.adaptive
+            .map(|(min_samples, max_samples, noise_threshold)| AdaptiveSampling { min_samples, max_samples, noise_threshold });
+        let clamp_indirect = args.clamp_indirect;
+        let outlier_rejection = args.outlier_rejection
+            .map(|(deviations, min_samples)| OutlierRejection { deviations, min_samples });
+        let debug = args.debug
+            .map(|name| DebugView::from_name(name.as_str()).ok_or_else(|| {
+                let message = DebugView::missing_reason(name.as_str())
+                    .map_or_else(|| format!("Unknown debug view '{}'", name.as_str()), String::from);
+                vm.new_value_error(message)
+            }))
+            .transpose()?;
+        // Rows are counted from the bottom one
+        let crop = args.crop
+            .map(|(x, y, crop_width, crop_height)| Tile::new(x..x + crop_width, height.saturating_sub(y + crop_height)..height.saturating_sub(y)));
 
         let scene_future = future::try_join_all(world_futures).map_ok(move |world| {
-            let scene = Scene {
-                camera,
-                width,
-                height,
-                world: HitList::new(world),
-                samples_per_px,
-                rays_per_sample,
+            let mut scene = Scene {
                 ambiant_color,
+                environment,
+                russian_roulette,
+                integrator: integrator.unwrap_or_default(),
+                spectral,
+                tone_map,
+                adaptive,
+                seed,
+                sampler,
+                filter,
+                clamp_indirect,
+                outlier_rejection,
+                debug,
+                crop,
+                ..Scene::new(camera, width, height, HitList::new(world), samples_per_px, rays_per_sample)
             };
+            if photon_mapping {
+                scene.integrator = Integrator::photon_mapping(&scene.world, photons, photon_radius, scene.max_depth(), Pcg32::seed_from_u64(seed));
+            }
             Rc::new(scene)
         });
 
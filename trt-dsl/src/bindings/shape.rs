@@ -3,15 +3,22 @@ use crate::future::PyFuture;
 use super::{
     float::FloatLike,
     material::{MaterialError, PyMaterial},
+    phase::PyPhase,
     vec3::PyVec3,
 };
 
 use trt_core::{
+    density::{Turbulence, VoxelGrid},
     hit::{RectBuilder, Sphere, HitBox, BVHNode, Cylinder},
+    material::PhaseFunction,
     prelude::*,
 };
 
+use rpy::function::OptionalArg;
+
 use futures::prelude::*;
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
 
 pub type SharedHit = PyFuture<Result<Rc<dyn Hit>, Rc<MaterialError>>>;
 
@@ -137,7 +144,8 @@ impl PyShape {
 
         let node_future = future::try_join_all(world_futures)
             .map_ok(|mut world| {
-                let node = BVHNode::new(&mut world, 0., 1.);
+                // Split axes only change the traversal speed, a fixed seed keeps builds reproducible
+                let node = BVHNode::new(&mut world, 0., 1., Pcg32::seed_from_u64(0));
                 Rc::new(node) as _
             });
 
@@ -170,7 +178,44 @@ impl PyShape {
     }
 
     #[pymethod]
-    fn constant_medium(&self, density: FloatLike, color: PyVec3) -> Self {
-        self.map(move |h| h.constant_medium(density.as_f32(), color.into_vec()))
+    fn constant_medium(&self, density: FloatLike, color: PyVec3, phase: OptionalArg<PyPhase>) -> Self {
+        let phase = phase_function(phase);
+        self.map(move |h| h.constant_medium_with_phase(density.as_f32(), color.into_vec(), phase))
+    }
+
+    #[pymethod]
+    fn turbulent_medium(&self, density: FloatLike, scale: FloatLike, color: PyVec3, phase: OptionalArg<PyPhase>) -> Self {
+        // Same noise on every run, like BVH splits
+        let turbulence = Turbulence::new(density.as_f32(), scale.as_f32(), Pcg32::seed_from_u64(0));
+        let phase = phase_function(phase);
+        self.map(move |h| h.heterogeneous_medium_with_phase(turbulence, color.into_vec(), phase))
     }
+
+    // Densities of `resolution` voxels filling the shape's bounding box, x first
+    #[pymethod]
+    fn voxel_medium(&self, resolution: (usize, usize, usize), values: PyListRef, color: PyVec3, phase: OptionalArg<PyPhase>, vm: &VirtualMachine) -> PyResult<Self> {
+        let (nx, ny, nz) = resolution;
+        let values = values
+            .borrow_elements()
+            .iter()
+            .map(|value| Ok(FloatLike::try_from_object(vm, value.clone())?.as_f32()))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        if nx * ny * nz == 0 || values.len() != nx * ny * nz {
+            let error_msg = format!("Expected {} densities for a {}x{}x{} grid, got {}", nx * ny * nz, nx, ny, nz, values.len());
+            return Err(vm.new_value_error(error_msg))
+        }
+
+        let phase = phase_function(phase);
+        Ok(self.map(move |h| {
+            let bbox = h.bounding_box(0., 1.).expect("shape without a bounding box");
+            let grid = VoxelGrid::new(resolution, values, bbox.min, bbox.max);
+            h.heterogeneous_medium_with_phase(grid, color.into_vec(), phase)
+        }))
+    }
+}
+
+// Media scatter isotropically unless told otherwise
+fn phase_function(phase: OptionalArg<PyPhase>) -> PhaseFunction {
+    phase.into_option().map(|phase| phase.0).unwrap_or_default()
 }
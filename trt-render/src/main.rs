@@ -1,5 +1,6 @@
-use rand::{random, thread_rng, Rng, SeedableRng};
-use indicatif::{ParallelProgressIterator, ProgressStyle, ProgressBar};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use indicatif::{ProgressStyle, ProgressBar};
 use rayon::prelude::*;
 
 use std::sync::Arc;
@@ -12,15 +13,188 @@ use trt_core::hit::{Sphere, MovingSphere, RectBuilder, HitBox, BVHNode};
 use trt_core::material::Lambertian;
 use trt_core::texture::{Constant, Checker, Noise, Image};
 use trt_core::world;
-use trt_core::scene::Scene;
+use trt_core::scene::{Scene, AdaptiveSampling, OutlierRejection};
+use trt_core::adaptive::AdaptiveRenderer;
+use trt_core::aov::{Aov, AovBuffers};
+use trt_core::debug::DebugView;
+use trt_core::denoise::Denoiser;
+use trt_core::environment::{EnvironmentMap, SharedEnvironment, Sky};
+use trt_core::accumulator::Accumulator;
+use trt_core::integrator::Integrator;
+use trt_core::sampler::{self, SharedSampler};
+use trt_core::framebuffer::Framebuffer;
+use trt_core::film::{Filter, FilterKind};
+use trt_core::tile::{Tile, TileOrder};
+use trt_core::tonemap::{ToneMap, ToneMapper};
 
 const WIDTH: usize = 300;
 const HEIGHT: usize = 300;
 const SAMPLES_PER_PX: u32 = 500;
 const RAYS_PER_SAMPLE: u32 = 50;
+const PHOTONS: usize = 1_000_000;
+const PHOTON_RADIUS: f32 = 5.;
+const WHITE_POINT: f32 = 4.;
+const MIN_SAMPLES: u32 = 16;
+const MAX_SAMPLES: u32 = 4 * SAMPLES_PER_PX;
+const NOISE_THRESHOLD: f32 = 0.01;
+const OUTLIER_MIN_SAMPLES: u32 = 16;
+const PREVIEW_PATH: &str = "./generated/preview.png";
+const AOV_SAMPLES: u32 = 16;
+const TILE_SIZE: usize = 32;
+
+// Value following `--name` on the command line
+fn arg(name: &str) -> Option<String> {
+    let flag = format!("--{}", name);
+    std::env::args()
+        .skip_while(|arg| *arg != flag)
+        .nth(1)
+}
+
+fn flag(name: &str) -> bool {
+    let flag = format!("--{}", name);
+    std::env::args().any(|arg| arg == flag)
+}
+
+// `--integrator path|bidirectional|photon|ao|whitted`, ambient occlusion reaching `--ao-radius` away
+fn integrator(scene: &Scene<impl Hit>, rng: impl Rng) -> Integrator {
+    match arg("integrator").as_deref() {
+        None | Some("path") => Integrator::PathTracing,
+        Some("bidirectional") => Integrator::Bidirectional,
+        Some("photon") => Integrator::photon_mapping(&scene.world, PHOTONS, PHOTON_RADIUS, scene.max_depth(), rng),
+        Some("ao") => Integrator::AmbientOcclusion {
+            radius: arg("ao-radius").map_or(std::f32::MAX, |radius| radius.parse().expect("Invalid ambient occlusion radius")),
+        },
+        Some("whitted") => Integrator::Whitted,
+        Some(other) => panic!("Unknown integrator '{}', expected path, bidirectional, photon, ao or whitted", other),
+    }
+}
+
+// `--sampler independent|stratified|halton|sobol`
+fn sampler() -> SharedSampler {
+    let name = arg("sampler").unwrap_or_else(|| "independent".to_string());
+    sampler::from_name(&name, SAMPLES_PER_PX)
+        .unwrap_or_else(|| panic!("Unknown sampler '{}', expected independent, stratified, halton or sobol", name))
+}
+
+// `--filter box|tent|gaussian|mitchell|lanczos` with `--filter-radius` in pixels
+fn filter() -> Filter {
+    let kind = match arg("filter") {
+        None => FilterKind::Box,
+        Some(name) => FilterKind::from_name(&name)
+            .unwrap_or_else(|| panic!("Unknown filter '{}', expected box, tent, gaussian, mitchell or lanczos", name)),
+    };
+    let radius = arg("filter-radius").map_or(kind.default_radius(), |radius| radius.parse().expect("Invalid filter radius"));
+
+    Filter { kind, radius }
+}
+
+// `--environment <map.hdr|map.exr>` with `--environment-rotation` in degrees and `--environment-intensity`,
+// or `--sky` with `--sun-elevation` and `--sun-azimuth` in degrees and `--turbidity`
+fn environment() -> Option<SharedEnvironment> {
+    let intensity = arg("environment-intensity").map_or(1., |intensity| intensity.parse().expect("Invalid environment intensity"));
+
+    if flag("sky") {
+        let elevation = arg("sun-elevation").map_or(45., |elevation| elevation.parse().expect("Invalid sun elevation"));
+        let azimuth = arg("sun-azimuth").map_or(180., |azimuth| azimuth.parse().expect("Invalid sun azimuth"));
+        let turbidity = arg("turbidity").map_or(3., |turbidity| turbidity.parse().expect("Invalid turbidity"));
+
+        return Some(Arc::new(Sky::new(elevation, azimuth, turbidity, intensity)))
+    }
+
+    let path = arg("environment")?;
+    let rotation = arg("environment-rotation").map_or(0., |rotation| rotation.parse().expect("Invalid environment rotation"));
+
+    let (width, height, pixels) = load_hdr_image(&path).unwrap_or_else(|error| {
+        eprintln!("Failed to load environment map '{}': {}", path, error);
+        std::process::exit(1)
+    });
+    Some(Arc::new(EnvironmentMap::new(width, height, pixels, rotation, intensity)))
+}
+
+// `--denoise` with `--denoise-iterations`, each one filtering twice as far
+fn denoiser() -> Denoiser {
+    let default = Denoiser::default();
+    let iterations = arg("denoise-iterations").map_or(default.iterations, |iterations| iterations.parse().expect("Invalid denoise iterations"));
+
+    Denoiser { iterations, ..default }
+}
+
+// `--tone-map clamp|reinhard|extended_reinhard|aces|hable`, `--white` and `--exposure` in stops
+fn tone_map() -> ToneMap {
+    let white = arg("white").map_or(WHITE_POINT, |white| white.parse().expect("Invalid white point"));
+    let mapper = match arg("tone-map") {
+        None => ToneMapper::Clamp,
+        Some(name) => ToneMapper::from_name(&name, white)
+            .unwrap_or_else(|| panic!("Unknown tone mapper '{}', expected clamp, reinhard, extended_reinhard, aces or hable", name)),
+    };
+    let exposure = arg("exposure").map_or(0., |exposure| exposure.parse().expect("Invalid exposure"));
+
+    ToneMap { mapper, exposure }
+}
+
+// `--adaptive` with `--noise-threshold`, `--min-samples` and `--max-samples`
+fn adaptive() -> Option<AdaptiveSampling> {
+    if !flag("adaptive") {
+        return None
+    }
 
-pub fn random_scene() -> impl Hit {
-    let mut rng = thread_rng();
+    Some(AdaptiveSampling {
+        min_samples: arg("min-samples").map_or(MIN_SAMPLES, |samples| samples.parse().expect("Invalid minimum samples")),
+        max_samples: arg("max-samples").map_or(MAX_SAMPLES, |samples| samples.parse().expect("Invalid maximum samples")),
+        noise_threshold: arg("noise-threshold").map_or(NOISE_THRESHOLD, |threshold| threshold.parse().expect("Invalid noise threshold")),
+    })
+}
+
+// `--debug normal|uv|distance|material_id`, or `traversal` when built with
+// the `traversal-stats` feature
+fn debug_view() -> Option<DebugView> {
+    let name = arg("debug")?;
+    let view = DebugView::from_name(&name).unwrap_or_else(|| {
+        if let Some(reason) = DebugView::missing_reason(&name) {
+            panic!("{}", reason)
+        }
+        let names = DebugView::ALL.iter().map(DebugView::name).collect::<Vec<_>>();
+        panic!("Unknown debug view '{}', expected {}", name, names.join(", "))
+    });
+
+    Some(view)
+}
+
+// `--crop <x>,<y>,<width>,<height>` in pixels from the image's top left corner
+fn crop() -> Option<Tile> {
+    let crop = arg("crop")?;
+    let values = crop.split(',').map(|value| value.parse().expect("Invalid crop")).collect::<Vec<usize>>();
+    let (x, y, width, height) = match values[..] {
+        [x, y, width, height] => (x, y, width, height),
+        _ => panic!("Invalid crop '{}', expected x,y,width,height", crop),
+    };
+
+    // Framebuffer rows start from the bottom one
+    let bottom = HEIGHT.saturating_sub(y + height);
+    Some(Tile::new(x..x + width, bottom..HEIGHT.saturating_sub(y)))
+}
+
+// `--tile-size <pixels>` and `--tile-order scanline|spiral|hilbert`
+fn tiling() -> (usize, TileOrder) {
+    let size = arg("tile-size").map_or(TILE_SIZE, |size| size.parse().expect("Invalid tile size"));
+    let order = match arg("tile-order") {
+        None => TileOrder::default(),
+        Some(name) => TileOrder::from_name(&name)
+            .unwrap_or_else(|| panic!("Unknown tile order '{}', expected scanline, spiral or hilbert", name)),
+    };
+
+    (size, order)
+}
+
+// `--reject-outliers <deviations>` with `--outlier-min-samples`
+fn outlier_rejection() -> Option<OutlierRejection> {
+    let deviations = arg("reject-outliers")?.parse().expect("Invalid outlier deviations");
+    let min_samples = arg("outlier-min-samples").map_or(OUTLIER_MIN_SAMPLES, |samples| samples.parse().expect("Invalid outlier minimum samples"));
+
+    Some(OutlierRejection { deviations, min_samples })
+}
+
+pub fn random_scene(rng: &mut impl Rng) -> impl Hit {
     let n = 500;
     let mut objects = Vec::<Arc<dyn ParallelHit>>::with_capacity(n);
 
@@ -37,8 +211,8 @@ pub fn random_scene() -> impl Hit {
 
     for a in -10..10 {
         for b in -10..10 {
-            let choose_mat = random::<f32>();
-            let center = Vec3::new(a as f32 + 0.9 * random::<f32>(), rng.gen_range(0.2, 5.0), b as f32 + 0.9 * random::<f32>());
+            let choose_mat = rng.gen::<f32>();
+            let center = Vec3::new(a as f32 + 0.9 * rng.gen::<f32>(), rng.gen_range(0.2, 5.0), b as f32 + 0.9 * rng.gen::<f32>());
 
             if (center - Vec3::new(4., 0.2, 0.)).len() > 0.9 {
                 if choose_mat < 0.5 {
@@ -46,11 +220,11 @@ pub fn random_scene() -> impl Hit {
                         Sphere::builder()
                             .center(center)
                             .radius(0.2)
-                            .matte(Vec3::random(rng) * Vec3::random(rng))
+                            .matte(Vec3::random(&mut *rng) * Vec3::random(&mut *rng))
                     ));
                 } else if choose_mat < 0.90 {
-                    let albedo = (Vec3::random(rng) + Vec3::splat(1.)) * 0.5;
-                    let fuzz = 0.5 * random::<f32>();
+                    let albedo = (Vec3::random(&mut *rng) + Vec3::splat(1.)) * 0.5;
+                    let fuzz = 0.5 * rng.gen::<f32>();
 
                     objects.push(Arc::new(
                         Sphere::builder()
@@ -99,11 +273,11 @@ pub fn random_scene() -> impl Hit {
             .metallic((1, 1, 1))
     ));
 
-    BVHNode::new(&mut objects, 0., 1.)
+    BVHNode::new(&mut objects, 0., 1., rng)
 }
 
-pub fn two_perlin_spheres() -> impl Hit {
-    let pertext = Noise::from_scale(5.);
+pub fn two_perlin_spheres(rng: &mut impl Rng) -> impl Hit {
+    let pertext = Noise::from_scale(5., rng);
 
     let earth_img = load_image("./assets/earthmap.jpg");
 
@@ -124,8 +298,8 @@ pub fn two_perlin_spheres() -> impl Hit {
     ]
 }
 
-pub fn simple_light() -> impl Hit {
-    let pertext = || Noise::from_scale(4.);
+pub fn simple_light(rng: &mut impl Rng) -> impl Hit {
+    let pertext = || Noise::from_scale(4., rng);
 
     let earth_img = load_image("./assets/earthmap.jpg");
 
@@ -200,7 +374,7 @@ pub fn cornell_smoke() -> impl Hit {
     ]
 }
 
-fn final_scene() -> impl Hit {
+fn final_scene(rng: &mut impl Rng) -> impl Hit {
     let mut boxlist = Vec::<Arc<dyn ParallelHit>>::new();
     let mut boxlist2 = Vec::<Arc<dyn ParallelHit>>::new();
 
@@ -215,7 +389,7 @@ fn final_scene() -> impl Hit {
             let y0 = 0.;
             let z0 = (-1000 + j * w) as f32;
             let x1 = x0 + w as f32;
-            let y1 = 100. * (random::<f32>() + 0.01);
+            let y1 = 100. * (rng.gen::<f32>() + 0.01);
             let z1 = z0 + w as f32;
             boxlist.push(Arc::new(HitBox::new(Vec3::new(x0, y0, z0), Vec3::new(x1, y1, z1), ground.clone())));
         }
@@ -226,7 +400,7 @@ fn final_scene() -> impl Hit {
     let ns = 1000;
     for _ in 0..ns {
         boxlist2.push(Arc::new(Sphere::builder()
-            .center((random::<f32>() * 165., random::<f32>() * 165. , random::<f32>() * 165.))
+            .center((rng.gen::<f32>() * 165., rng.gen::<f32>() * 165. , rng.gen::<f32>() * 165.))
             .radius(20)
             .matte(white)
         ))
@@ -237,10 +411,10 @@ fn final_scene() -> impl Hit {
         .center((360, 150, 145))
         .radius(70)
         .dielectric(1.5);
-    let pertext = Noise::from_scale(0.1);
+    let pertext = Noise::from_scale(0.1, &mut *rng);
 
     world![
-        BVHNode::new(&mut boxlist, 0., 1.),
+        BVHNode::new(&mut boxlist, 0., 1., &mut *rng),
         RectBuilder
             .x(123..=423)
             .z(147..=412)
@@ -273,13 +447,21 @@ fn final_scene() -> impl Hit {
             .center((220, 280, 300))
             .radius(80)
             .material(Lambertian::new(pertext)),
-        BVHNode::new(&mut boxlist2, 0., 1.)
+        BVHNode::new(&mut boxlist2, 0., 1., &mut *rng)
             .rotate_y(15.)
             .translate((-100., 270., 395.)),
     ]
 }
 
-fn run() -> image::RgbImage {
+struct Render {
+    framebuffer: Framebuffer,
+    // Sample count heatmap of adaptive renders
+    heatmap: Option<Framebuffer>,
+    aovs: Option<AovBuffers>,
+    denoised: Option<Framebuffer>,
+}
+
+fn run() -> Render {
     use std::time::Instant;
 
     let now = Instant::now();
@@ -290,37 +472,165 @@ fn run() -> image::RgbImage {
         .dimensions(WIDTH as f32, HEIGHT as f32)
         .finish();
 
-    let scene = Scene {
-        camera,
-        width: WIDTH,
-        height: HEIGHT,
-        world: final_scene(),
-        samples_per_px: SAMPLES_PER_PX,
-        rays_per_sample: RAYS_PER_SAMPLE,
-        ambiant_color: Vec3::splat(0),
+    // `--seed` reproduces a previous render, scene construction included
+    let seed = arg("seed").map_or_else(rand::random, |seed| seed.parse().expect("Invalid seed"));
+    println!("Seed: {}", seed);
+
+    let mut rng = Pcg32::seed_from_u64(seed);
+    let world = final_scene(&mut rng);
+
+    let mut scene = Scene {
+        environment: environment(),
+        spectral: flag("spectral"),
+        tone_map: tone_map(),
+        adaptive: adaptive(),
+        seed,
+        sampler: sampler(),
+        filter: filter(),
+        // `--clamp-indirect <max>`
+        clamp_indirect: arg("clamp-indirect").map(|clamp| clamp.parse().expect("Invalid indirect clamp")),
+        outlier_rejection: outlier_rejection(),
+        debug: debug_view(),
+        crop: crop(),
+        ..Scene::new(camera, WIDTH, HEIGHT, world, SAMPLES_PER_PX, RAYS_PER_SAMPLE)
+    };
+    // Photons are traced as deep as the scene's paths
+    scene.integrator = integrator(&scene, &mut rng);
+
+    let (framebuffer, heatmap) = render(&scene);
+    println!("Elapsed: {:?}", now.elapsed());
+
+    // `--aovs` also saves the first hits' albedo, normal, depth, etc. which
+    // `--denoise` guides its filter with
+    let aovs = if flag("aovs") || flag("denoise") { Some(render_aovs(&scene)) } else { None };
+    let denoised = match &aovs {
+        Some(aovs) if flag("denoise") => Some(denoiser().denoise(&framebuffer, aovs)),
+        _ => None,
     };
 
-    let progress = ProgressBar::new((WIDTH * HEIGHT) as u64)
-        .with_style(ProgressStyle::default_bar().template("{pos:>7}/{len:7} {bar:40.cyan/yellow} - [{elapsed_precise}] [{eta_precise}]"));
+    Render { framebuffer, heatmap, aovs, denoised }
+}
+
+fn render(scene: &Scene<impl ParallelHit>) -> (Framebuffer, Option<Framebuffer>) {
+    if scene.adaptive.is_some() {
+        let (framebuffer, heatmap) = run_adaptive(scene);
+        return (framebuffer, Some(heatmap))
+    }
+
+    // `--progressive <samples>` refines the whole image by that many samples
+    // per pass, saving a preview after each one
+    if let Some(pass_samples) = arg("progressive") {
+        let pass_samples = pass_samples.parse().expect("Invalid samples per pass");
+        return (run_progressive(scene, pass_samples), None)
+    }
 
-    let rng = rand::rngs::SmallRng::from_entropy();
-    let bytes = (0..HEIGHT)
+    let (size, order) = tiling();
+    let progress = ProgressBar::new(scene.tiles(size, order).len() as u64)
+        .with_style(ProgressStyle::default_bar().template("{pos:>7}/{len:7} tiles {bar:40.cyan/yellow} - [{elapsed_precise}] [{eta_precise}]"));
+
+    let mut accumulator = scene.accumulator();
+    accumulate(scene, &mut accumulator, scene.samples_per_px, progress);
+
+    (accumulator.framebuffer(), None)
+}
+
+fn render_aovs(scene: &Scene<impl ParallelHit>) -> AovBuffers {
+    let pixels = (0..scene.height)
+        .into_par_iter()
+        .flat_map(|j| (0..scene.width).into_par_iter().map(move |i| (i, j)))
+        .map(|pixel| scene.pixel_aovs(pixel, 0..AOV_SAMPLES.min(scene.samples_per_px)))
+        .collect();
+
+    AovBuffers::from_pixels(scene.width, scene.height, pixels)
+}
+
+fn run_progressive(scene: &Scene<impl ParallelHit>, pass_samples: u32) -> Framebuffer {
+    let pass_samples = pass_samples.max(1);
+    let mut accumulator = scene.accumulator();
+    let passes = (scene.samples_per_px + pass_samples - 1) / pass_samples;
+
+    let progress = ProgressBar::new(passes as u64)
+        .with_style(ProgressStyle::default_bar().template("{pos:>7}/{len:7} passes {bar:40.cyan/yellow} - [{elapsed_precise}] [{eta_precise}]"));
+
+    for pass in 0..passes {
+        let samples = pass_samples.min(scene.samples_per_px - pass * pass_samples);
+        accumulate(scene, &mut accumulator, samples, ProgressBar::hidden());
+
+        save_png(&accumulator.framebuffer(), scene.tone_map, PREVIEW_PATH);
+        progress.inc(1);
+    }
+
+    progress.finish();
+
+    accumulator.framebuffer()
+}
+
+// Tiles are rendered in parallel into films of their own covering the
+// neighbouring pixels their samples splat into, then merged back in tile
+// order. Films of wide filters overlap, so a fixed order keeps the sums,
+// and seeded images, the same from run to run
+fn accumulate(scene: &Scene<impl ParallelHit>, accumulator: &mut Accumulator, samples: u32, progress: ProgressBar) {
+    let (size, order) = tiling();
+    let tiles = scene
+        .tiles(size, order)
         .into_par_iter()
-        .rev()
-        .flat_map(|j| (0..WIDTH).into_par_iter().map(move |i| (i, j)))
-        .map_with(rng, |rng, (i, j)| scene.pixel_color((i, j), rng))
-        .progress_with(progress)
-        .flat_map(|Color(r, g, b)| {
-            use rayon::iter::once;
-
-            once(r).chain(once(g)).chain(once(b))
+        .map(|tile| {
+            let mut film = accumulator.film().region(tile.xs.clone(), tile.ys.clone());
+            let estimates = tile
+                .pixels()
+                .map(|pixel| (pixel, scene.refine_pixel(&mut film, pixel, accumulator.get(pixel), samples)))
+                .collect::<Vec<_>>();
+
+            progress.inc(1);
+            (film, estimates)
         })
         .collect::<Vec<_>>();
 
-    println!("Elapsed: {:?}", now.elapsed());
+    progress.finish();
 
-    image::RgbImage::from_vec(WIDTH as u32, HEIGHT as u32, bytes)
-        .expect("Image and buffer dimension mismatch")
+    for (film, estimates) in tiles {
+        accumulator.merge_film(&film);
+        for (pixel, estimate) in estimates {
+            accumulator.add(pixel, &estimate);
+        }
+    }
+}
+
+fn run_adaptive(scene: &Scene<impl ParallelHit>) -> (Framebuffer, Framebuffer) {
+    let adaptive = scene.adaptive.expect("Scene isn't adaptively sampled");
+    let mut renderer = AdaptiveRenderer::new(scene.width, scene.height, scene.filter, adaptive, scene.sample_budget())
+        .cropped(scene.region());
+
+    let progress = ProgressBar::new(scene.sample_budget())
+        .with_style(ProgressStyle::default_bar().template("{pos:>9}/{len:9} samples {bar:40.cyan/yellow} - [{elapsed_precise}] [{eta_precise}]"));
+
+    loop {
+        let pass = renderer.next_pass();
+        if pass.is_empty() {
+            break
+        }
+
+        let estimates = pass
+            .into_par_iter()
+            .map(|(pixel, samples)| {
+                let count = samples.len() as u64;
+                let mut film = renderer.accumulator().film().region(pixel.0..pixel.0 + 1, pixel.1..pixel.1 + 1);
+                let estimate = scene.refine_pixel(&mut film, pixel, renderer.accumulator().get(pixel), count as u32);
+                progress.inc(count);
+                (pixel, estimate, film)
+            })
+            .collect::<Vec<_>>();
+
+        for (pixel, estimate, film) in estimates {
+            renderer.record(pixel, &estimate);
+            renderer.merge_film(&film);
+        }
+    }
+
+    progress.finish();
+    println!("Samples: {}/{}", renderer.spent(), scene.sample_budget());
+
+    (renderer.framebuffer(), renderer.sample_heatmap())
 }
 
 fn load_image(path: impl AsRef<Path>) -> Image {
@@ -332,8 +642,60 @@ fn load_image(path: impl AsRef<Path>) -> Image {
     Image::load(img.into_vec(), width as _, height as _)
 }
 
+// Linear radiance of a Radiance HDR or OpenEXR image, rows from the top one
+fn load_hdr_image(path: &str) -> Result<(usize, usize, Vec<Vec3>), Box<dyn std::error::Error>> {
+    // `image` only reads OpenEXR from 0.24 on
+    let (width, height, pixels) = if path.ends_with(".exr") {
+        let image = exr::prelude::read_first_rgba_layer_from_file(
+            path,
+            |resolution, _| (resolution.width(), vec![Vec3::splat(0.); resolution.area()]),
+            |(width, pixels), position, (r, g, b, _): (f32, f32, f32, f32)| pixels[position.x() + position.y() * *width] = Vec3::new(r, g, b),
+        )?;
+
+        let size = image.layer_data.size;
+        let (_, pixels) = image.layer_data.channel_data.pixels;
+        (size.width(), size.height(), pixels)
+    } else {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let decoder = image::codecs::hdr::HdrDecoder::new(file)?;
+        let metadata = decoder.metadata();
+        let pixels = decoder.read_image_hdr()?
+            .into_iter()
+            .map(|image::Rgb([r, g, b])| Vec3::new(r, g, b))
+            .collect();
+
+        (metadata.width as usize, metadata.height as usize, pixels)
+    };
+
+    if width == 0 || height == 0 {
+        return Err("the image is empty".into())
+    }
+
+    Ok((width, height, pixels))
+}
+
+fn save_hdr(framebuffer: &Framebuffer, path: &str) {
+    let pixels = framebuffer.image_rows()
+        .flatten()
+        .map(|pixel| image::Rgb([pixel.x(), pixel.y(), pixel.z()]))
+        .collect::<Vec<_>>();
+
+    let file = std::fs::File::create(path).expect("Failed to create HDR image");
+    image::codecs::hdr::HdrEncoder::new(std::io::BufWriter::new(file))
+        .encode(&pixels, framebuffer.width(), framebuffer.height())
+        .expect("Failed to save HDR image")
+}
+
+fn save_png(framebuffer: &Framebuffer, tone_map: ToneMap, path: &str) {
+    let image = image::RgbImage::from_vec(framebuffer.width() as u32, framebuffer.height() as u32, framebuffer.to_rgb8(tone_map))
+        .expect("Image and buffer dimension mismatch");
+
+    image.save(path)
+        .expect("Failed to save image")
+}
+
 fn main() {
-    let image = run();
+    let Render { framebuffer, heatmap, aovs, denoised } = run();
 
     let epoch_secs = time::SystemTime::now()
         .duration_since(time::UNIX_EPOCH)
@@ -342,6 +704,23 @@ fn main() {
 
     let path = format!("./generated/{}.png", epoch_secs);
 
-    image.save(path)
-        .expect("Failed to save image")
+    if flag("hdr") {
+        save_hdr(&framebuffer, &format!("./generated/{}.hdr", epoch_secs));
+    }
+
+    if let Some(heatmap) = heatmap {
+        save_png(&heatmap, ToneMap::default(), &format!("./generated/{}-samples.png", epoch_secs));
+    }
+
+    if let Some(denoised) = denoised {
+        save_png(&denoised, tone_map(), &format!("./generated/{}-denoised.png", epoch_secs));
+    }
+
+    if let Some(aovs) = aovs.filter(|_| flag("aovs")) {
+        for &aov in Aov::ALL.iter() {
+            save_png(&aovs.framebuffer(aov), ToneMap::default(), &format!("./generated/{}-{}.png", epoch_secs, aov.name()));
+        }
+    }
+
+    save_png(&framebuffer, tone_map(), &path)
 }
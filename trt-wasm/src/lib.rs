@@ -4,9 +4,10 @@ use std::{rc::Rc, future::Future};
 
 use wasm_bindgen::prelude::*;
 
-use trt_core::prelude::*;
+use trt_core::{accumulator::Accumulator, aov::{Aov, AovBuffers}, denoise::Denoiser, integrator::Integrator, prelude::*, tile::{Tile, TileOrder}};
 use trt_dsl::{DynScene, DynSceneResult, EvalOutput};
-use rand::{SeedableRng, prelude::SmallRng};
+
+const AOV_SAMPLES: u32 = 16;
 
 #[wasm_bindgen]
 pub fn setup_panic_hook() {
@@ -57,7 +58,9 @@ impl WasmEvalOutput {
                 let dyn_scene = scene_fut.await
                     .map_err(|e| format!("{:?}", e))?;
 
-                Ok(Some(Scene(dyn_scene, SmallRng::from_entropy())))
+                let accumulator = dyn_scene.accumulator();
+                let integrator = dyn_scene.integrator.clone();
+                Ok(Some(Scene(dyn_scene, accumulator, None, integrator)))
             },
             None => Ok(None)
         }
@@ -66,8 +69,9 @@ impl WasmEvalOutput {
 
 type SceneFuture = impl Future<Output = DynSceneResult>;
 
+// Also keeps the integrator the script picked, to switch back to it
 #[wasm_bindgen]
-pub struct Scene(Rc<DynScene>, SmallRng);
+pub struct Scene(Rc<DynScene>, Accumulator, Option<AovBuffers>, Integrator);
 
 #[wasm_bindgen]
 impl Scene {
@@ -78,10 +82,135 @@ impl Scene {
     }
 
     pub fn pixel_color(&mut self, x: usize, y: usize) -> u32 {
-        let Color(r, g, b) = self.0.pixel_color((x, y), &mut self.1);
+        let Color(r, g, b) = self.0.pixel_color((x, y));
         u32::from_be_bytes([0, r, g, b])
     }
 
+    pub fn row_radiance(&mut self, y: usize) -> Vec<f32> {
+        (0..self.0.width)
+            .flat_map(|x| self.pixel_radiance(x, y))
+            .collect()
+    }
+
+    pub fn pixel_radiance(&mut self, x: usize, y: usize) -> Vec<f32> {
+        let radiance = self.0.pixel_radiance((x, y));
+        vec![radiance.x(), radiance.y(), radiance.z()]
+    }
+
+    // Refines the row by `samples` more samples, returning its colors so far.
+    // Filters wider than a pixel also splat into the neighbouring rows
+    pub fn accumulate_row(&mut self, y: usize, samples: u32) -> Vec<u32> {
+        self.0.accumulate_row(&mut self.1, y, samples);
+        self.accumulated_row_color(y)
+    }
+
+    pub fn accumulated_row_color(&self, y: usize) -> Vec<u32> {
+        (0..self.0.width)
+            .map(|x| {
+                let Color(r, g, b) = Color::from_linear(self.0.tone_map.apply(self.1.radiance((x, y))));
+                u32::from_be_bytes([0, r, g, b])
+            })
+            .collect()
+    }
+
+    pub fn accumulated_row_radiance(&self, y: usize) -> Vec<f32> {
+        (0..self.0.width)
+            .flat_map(|x| {
+                let radiance = self.1.radiance((x, y));
+                vec![radiance.x(), radiance.y(), radiance.z()]
+            })
+            .collect()
+    }
+
+    // Tiles covering the scene's crop region or whole image in `order`:
+    // scanline, spiral or hilbert, each one as its x, y, width and height
+    pub fn tiles(&self, size: usize, order: &str) -> Result<Vec<u32>, JsValue> {
+        let order = TileOrder::from_name(order).ok_or_else(|| format!("Unknown tile order '{}'", order))?;
+
+        Ok(self.0
+            .tiles(size, order)
+            .into_iter()
+            .flat_map(|tile| vec![tile.xs.start as u32, tile.ys.start as u32, tile.width() as u32, tile.height() as u32])
+            .collect())
+    }
+
+    // Refines the tile by `samples` more samples, returning its colors so
+    // far row after row from the bottom one, e.g. to only re-render the
+    // area an edit touched
+    pub fn accumulate_tile(&mut self, x: usize, y: usize, width: usize, height: usize, samples: u32) -> Vec<u32> {
+        let tile = Tile::new(x..x + width, y..y + height).intersect(&Tile::new(0..self.0.width, 0..self.0.height));
+        self.0.accumulate_tile(&mut self.1, &tile, samples);
+
+        tile.pixels()
+            .map(|pixel| {
+                let Color(r, g, b) = Color::from_linear(self.0.tone_map.apply(self.1.radiance(pixel)));
+                u32::from_be_bytes([0, r, g, b])
+            })
+            .collect()
+    }
+
+    pub fn accumulated_samples(&self, x: usize, y: usize) -> u32 {
+        self.1.samples((x, y))
+    }
+
+    pub fn reset_accumulation(&mut self) {
+        self.1.clear()
+    }
+
+    // Renders with another integrator from now on, restarting the
+    // accumulation: path, bidirectional, ao or whitted, photon for scripts
+    // which picked photon mapping, and script for the one they picked.
+    // Ambient occlusion reaches `ao_radius` away, without bounds by default
+    pub fn set_integrator(&mut self, name: &str, ao_radius: Option<f32>) -> Result<(), JsValue> {
+        let integrator = match name {
+            "script" => self.3.clone(),
+            "path" => Integrator::PathTracing,
+            "bidirectional" => Integrator::Bidirectional,
+            "ao" => Integrator::AmbientOcclusion { radius: ao_radius.unwrap_or(std::f32::MAX) },
+            "whitted" => Integrator::Whitted,
+            "photon" => match &self.3 {
+                Integrator::PhotonMapping(_) => self.3.clone(),
+                _ => return Err("Photon mapping needs the script to pick it".into()),
+            },
+            other => return Err(format!("Unknown integrator '{}'", other).into()),
+        };
+
+        Rc::make_mut(&mut self.0).integrator = integrator;
+        self.1.clear();
+        Ok(())
+    }
+
+    // Raw values of an AOV over the whole image, rows going from the bottom
+    // one up with `channels` values per pixel: 3 for albedo, normal and
+    // position, 2 for uv and 1 for depth, material_id and object_id
+    pub fn aov(&mut self, name: &str) -> Result<Vec<f32>, JsValue> {
+        let aov = Aov::from_name(name).ok_or_else(|| format!("Unknown AOV '{}'", name))?;
+
+        Ok(self.aovs().values(aov))
+    }
+
+    fn aovs(&mut self) -> &AovBuffers {
+        let scene = &self.0;
+        self.2.get_or_insert_with(|| scene.render_aovs(AOV_SAMPLES.min(scene.samples_per_px)))
+    }
+
+    // Colors of the accumulated image once denoised, rows going from the
+    // bottom one up, typically called after a finished progressive pass
+    pub fn denoised_color(&mut self) -> Vec<u32> {
+        let tone_map = self.0.tone_map;
+        let framebuffer = self.1.framebuffer();
+        let denoised = Denoiser::default().denoise(&framebuffer, self.aovs());
+
+        denoised
+            .pixels()
+            .iter()
+            .map(|&radiance| {
+                let Color(r, g, b) = Color::from_linear(tone_map.apply(radiance));
+                u32::from_be_bytes([0, r, g, b])
+            })
+            .collect()
+    }
+
     pub fn width(&self) -> u32 {
         self.0.width as _
     }
@@ -97,6 +97,12 @@ class App extends React.Component<{}, IAppState> {
     return result;
   }
 
+  async switchIntegrator(integrator: string) {
+    this.setState({ ...this.state, rendering: true });
+    await this.state.wasmExecutor.setIntegrator(integrator);
+    this.setState({ ...this.state, rendering: false });
+  }
+
   onSceneCodeChanged(code: string) {
     saveLastSource(code);
     this.sceneCode = code;
@@ -158,6 +164,19 @@ class App extends React.Component<{}, IAppState> {
                   );
                 })}
               </select>
+              <select
+                className="integrator-select"
+                onChange={(e) => this.switchIntegrator(e.target.value)}
+                disabled={this.state.rendering || this.state.runningScript}
+              >
+                {integrators.map((integrator, idx) => {
+                  return (
+                    <option value={integrator} key={idx}>
+                      {integrator}
+                    </option>
+                  );
+                })}
+              </select>
             </div>
             <Editor
               initialSource={this.sceneCode}
@@ -178,6 +197,9 @@ class App extends React.Component<{}, IAppState> {
   }
 }
 
+// As named by `Scene::set_integrator`, the script's own first
+const integrators = ["script", "path", "bidirectional", "ao", "whitted", "photon"];
+
 const LAST_SOURCE_STORAGE_KEY = "last-source";
 
 function loadLastSource() {
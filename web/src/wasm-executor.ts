@@ -19,6 +19,10 @@ export class WasmExecutor {
   workers: Comlink.Remote<WasmWorker>[];
   enabledWorkers: number = 0;
   cancelCurrentRender: boolean = false;
+  // Used instead of the one the script picks
+  integrator: string | null = null;
+  // Of the scene the workers hold, once rendered
+  sceneSize: SceneSize | null = null;
 
   events = createNanoEvents<ExecutorEvents>();
 
@@ -49,6 +53,40 @@ export class WasmExecutor {
   }
 
   async render(sceneSize: SceneSize, sceneCode: string) {
+    this.sceneSize = sceneSize;
+
+    await this.renderRows(sceneSize, async (worker, i) => {
+      if (i != 0) await worker.eval(sceneCode);
+      return await this.applyIntegrator(worker, i);
+    });
+  }
+
+  // Renders the scene the workers already hold again with another
+  // integrator, without evaluating the script
+  async setIntegrator(name: string) {
+    this.integrator = name;
+    if (this.sceneSize === null) return;
+
+    await this.renderRows(this.sceneSize, (worker, i) =>
+      this.applyIntegrator(worker, i)
+    );
+  }
+
+  // Whether the worker can render with the integrator
+  async applyIntegrator(worker: Comlink.Remote<WasmWorker>, i: number) {
+    if (this.integrator === null) return true;
+
+    const error = await worker.setIntegrator(this.integrator);
+    if (error !== null && i == 0) this.events.emit("evalError", error);
+
+    return error === null;
+  }
+
+  // Rows are shared out to the workers once they are `prepare`d
+  async renderRows(
+    sceneSize: SceneSize,
+    prepare: (worker: Comlink.Remote<WasmWorker>, i: number) => Promise<boolean>
+  ) {
     this.cancelCurrentRender = false;
 
     let { width, height } = sceneSize;
@@ -58,7 +96,7 @@ export class WasmExecutor {
     let startTime = performance.now();
     let rows = height - 1;
     let work = this.workers.map(async (worker, i) => {
-      if (i != 0) await worker.eval(sceneCode);
+      if (!(await prepare(worker, i))) return;
 
       while (rows >= 0) {
         if (this.cancelCurrentRender) {
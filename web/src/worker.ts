@@ -99,6 +99,19 @@ export class WasmWorker {
     }
   }
 
+  // Error message of the scene when it doesn't know the integrator, keeping
+  // the scene evaluated last
+  setIntegrator(name: string, aoRadius?: number): string | null {
+    if (this.scene === null) return null;
+
+    try {
+      this.scene.set_integrator(name, aoRadius);
+      return null;
+    } catch (error) {
+      return String(error);
+    }
+  }
+
   localNames(): string[] {
     if (this.vm === null) return [];
 